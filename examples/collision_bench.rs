@@ -0,0 +1,129 @@
+// Times the block collision test for many ball positions over a 20x40
+// layout, checking every block against looking only at the `SpatialGrid`
+// candidates, and counts how many precise tests the bounding-box broad
+// phase saves:
+//
+//     cargo run --release --example collision_bench -- [queries]
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use arkanoid::{
+    check_circle_rectangle_collision_f32, circle_bounds_overlap_rect, grid_layout, Block, Rng,
+    SpatialGrid,
+};
+
+const ROWS: u32 = 20;
+const COLS: u32 = 40;
+const RADIUS: f32 = 15.0;
+
+fn hits(blocks: &[Block], indices: impl Iterator<Item = usize>, (x, y): (f32, f32)) -> usize {
+    indices
+        .filter(|&index| {
+            let block = &blocks[index];
+            check_circle_rectangle_collision_f32(
+                x,
+                y,
+                RADIUS,
+                block.rect_x1,
+                block.rect_y1,
+                block.rect_x2,
+                block.rect_y2,
+            )
+            .is_some()
+        })
+        .count()
+}
+
+// Blocks among `indices` that pass the broad phase and get the precise test
+fn broad_phase_passes(
+    blocks: &[Block],
+    indices: impl Iterator<Item = usize>,
+    (x, y): (f32, f32),
+) -> usize {
+    indices
+        .filter(|&index| {
+            let block = &blocks[index];
+            let rect = (block.rect_x1, block.rect_y1, block.rect_x2, block.rect_y2);
+            circle_bounds_overlap_rect(x, y, RADIUS, rect)
+        })
+        .count()
+}
+
+fn time(label: &str, run: impl Fn() -> usize) -> Duration {
+    let start = Instant::now();
+    let hits = black_box(run());
+    let elapsed = start.elapsed();
+    println!("{:12} {:>10.2?}  ({} hits)", label, elapsed, hits);
+    elapsed
+}
+
+fn main() -> Result<(), String> {
+    let queries: usize = match std::env::args().nth(1) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("Invalid number of queries: {}", value))?,
+        None => 100_000,
+    };
+
+    let blocks = grid_layout(ROWS, COLS, 15.0, 10.0, 5.0, (2.5, 50.0));
+    let grid = SpatialGrid::new(&blocks);
+    let mut rng = Rng::new(0);
+    let positions: Vec<(f32, f32)> = (0..queries)
+        .map(|_| (rng.range(0.0, 800.0), rng.range(0.0, 500.0)))
+        .collect();
+
+    println!(
+        "{} ball positions over {} blocks ({}x{})",
+        queries,
+        blocks.len(),
+        ROWS,
+        COLS
+    );
+    let brute = time("brute force", || {
+        positions
+            .iter()
+            .map(|&position| hits(&blocks, 0..blocks.len(), position))
+            .sum()
+    });
+    let gridded = time("grid", || {
+        positions
+            .iter()
+            .map(|&(x, y)| {
+                let candidates = grid.candidates(x - RADIUS, y - RADIUS, x + RADIUS, y + RADIUS);
+                hits(&blocks, candidates.into_iter(), (x, y))
+            })
+            .sum()
+    });
+    println!(
+        "speedup      {:.1}x",
+        brute.as_secs_f64() / gridded.as_secs_f64()
+    );
+
+    // With one ball, each position stands for a frame
+    let (mut candidates, mut precise) = (0, 0);
+    for &(x, y) in &positions {
+        let indices = grid.candidates(x - RADIUS, y - RADIUS, x + RADIUS, y + RADIUS);
+        candidates += indices.len();
+        precise += broad_phase_passes(&blocks, indices.into_iter(), (x, y));
+    }
+    let brute_precise: usize = positions
+        .iter()
+        .map(|&position| broad_phase_passes(&blocks, 0..blocks.len(), position))
+        .sum();
+    let per_frame = |count: usize| count as f64 / queries.max(1) as f64;
+    println!("precise tests per frame:");
+    println!(
+        "  brute force                {:>8.2}",
+        per_frame(blocks.len() * queries)
+    );
+    println!(
+        "  brute force + broad phase  {:>8.2}",
+        per_frame(brute_precise)
+    );
+    println!(
+        "  grid                       {:>8.2}",
+        per_frame(candidates)
+    );
+    println!("  grid + broad phase         {:>8.2}", per_frame(precise));
+    Ok(())
+}
@@ -0,0 +1,75 @@
+// Plays many headless games with one controller and prints how it did on
+// average, for comparing controllers and tuning them:
+//
+//     cargo run --release --example headless_stats -- [games] [controller] [max ticks]
+//
+// With the defaults, the predictive controller wins about 90% of games and
+// the follow controller about 65%, while the bounce controller loses them
+// all. A clear drop from that means a change has made the AI, or the
+// physics it relies on, worse.
+use arkanoid::{GameConfig, GameState, MainState, PaddleController};
+
+// Fifteen minutes of play, after which a game still going counts as timed
+// out; a predictive game usually takes about nine to win
+const DEFAULT_MAX_TICKS: usize = 60 * 60 * 15;
+
+fn main() -> Result<(), String> {
+    let mut args = std::env::args().skip(1);
+    let games: u64 = match args.next() {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("Invalid number of games: {}", value))?,
+        None => 1000,
+    };
+    let controller: PaddleController = match args.next() {
+        Some(name) => name.parse()?,
+        None => PaddleController::Predictive,
+    };
+    let max_ticks = match args.next() {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("Invalid tick limit: {}", value))?,
+        None => DEFAULT_MAX_TICKS,
+    };
+
+    let (mut wins, mut losses, mut timeouts, mut failures) = (0, 0, 0, 0);
+    let (mut score, mut ticks, mut blocks) = (0u64, 0u64, 0u64);
+    for seed in 0..games {
+        let mut game = MainState::new_seeded(GameConfig::default(), seed);
+        game.controller = controller.build();
+        match game.run_headless(max_ticks) {
+            Ok(metrics) => {
+                match game.state {
+                    GameState::Won => wins += 1,
+                    GameState::GameOver => losses += 1,
+                    _ => timeouts += 1,
+                }
+                score += metrics.score as u64;
+                ticks += metrics.ticks as u64;
+                blocks += metrics.blocks_destroyed as u64;
+            }
+            Err(e) => {
+                eprintln!("seed {}: {}", seed, e);
+                failures += 1;
+            }
+        }
+    }
+
+    let finished = (games - failures).max(1) as f64;
+    let share = |count: u64| 100.0 * count as f64 / finished;
+    println!("{} games with the {} controller", games, controller.name());
+    println!("won:          {:.1}%", share(wins));
+    println!("lost:         {:.1}%", share(losses));
+    println!(
+        "timed out:    {:.1}% (still going after {} ticks)",
+        share(timeouts),
+        max_ticks
+    );
+    println!("mean score:   {:.1}", score as f64 / finished);
+    println!("mean ticks:   {:.1}", ticks as f64 / finished);
+    println!("mean blocks:  {:.1}", blocks as f64 / finished);
+    if failures > 0 {
+        println!("failed runs:  {}", failures);
+    }
+    Ok(())
+}
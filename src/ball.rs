@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+use crate::block::Block;
+
+/// Velocities are in pixels per second.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ball {
+    pub x: f32,
+    pub y: f32,
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    /// Consecutive ticks spent moving almost horizontally
+    #[serde(default)]
+    pub stalled_ticks: u32,
+    /// Consecutive ticks spent moving almost vertically
+    #[serde(default)]
+    pub vertical_stall_ticks: u32,
+    /// Set while the ball is stuck to a sticky paddle: how far right of the
+    /// paddle's left edge it sits
+    #[serde(default)]
+    pub stuck_offset: Option<f32>,
+}
+
+impl Ball {
+    pub fn new(x: f32, y: f32, velocity_x: f32, velocity_y: f32) -> Self {
+        Ball {
+            x,
+            y,
+            velocity_x,
+            velocity_y,
+            stalled_ticks: 0,
+            vertical_stall_ticks: 0,
+            stuck_offset: None,
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.velocity_x.hypot(self.velocity_y)
+    }
+
+    /// Limits the horizontal component and then the overall speed to `max_speed`.
+    pub fn clamp_speed(&mut self, max_speed: f32) {
+        self.velocity_x = self.velocity_x.clamp(-max_speed, max_speed);
+
+        let speed = self.speed();
+        if speed > max_speed {
+            let scale = max_speed / speed;
+            self.velocity_x *= scale;
+            self.velocity_y *= scale;
+        }
+    }
+
+    /// Turns the ball's direction by `degrees` clockwise on screen, keeping
+    /// its speed.
+    pub fn rotate(&mut self, degrees: f32) {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (x, y) = (self.velocity_x, self.velocity_y);
+        self.velocity_x = x * cos - y * sin;
+        self.velocity_y = x * sin + y * cos;
+    }
+
+    /// A ball in the same place moving at the same speed, its direction
+    /// turned by `degrees` clockwise on screen.
+    pub fn rotated(&self, degrees: f32) -> Ball {
+        let mut ball = Ball::new(self.x, self.y, self.velocity_x, self.velocity_y);
+        ball.rotate(degrees);
+        ball
+    }
+
+    /// Speeds a moving ball up to `min_speed` if it is any slower, keeping
+    /// its direction.
+    pub fn keep_speed_above(&mut self, min_speed: f32) {
+        let speed = self.speed();
+        if speed > 0.0 && speed < min_speed {
+            let scale = min_speed / speed;
+            self.velocity_x *= scale;
+            self.velocity_y *= scale;
+        }
+    }
+
+    /// Whether the ball is moving into the face of `block` it is nearest on
+    /// each axis: the left or right face for x, the top or bottom for y.
+    pub fn moving_into(&self, block: &Block, radius: f32) -> (bool, bool) {
+        let near_left = self.x + radius - block.rect_x1 < block.rect_x2 - (self.x - radius);
+        let near_top = self.y + radius - block.rect_y1 < block.rect_y2 - (self.y - radius);
+        let into_x = if near_left {
+            self.velocity_x > 0.0
+        } else {
+            self.velocity_x < 0.0
+        };
+        let into_y = if near_top {
+            self.velocity_y > 0.0
+        } else {
+            self.velocity_y < 0.0
+        };
+        (into_x, into_y)
+    }
+
+    /// Elastic collision with another ball of the same radius and mass: the
+    /// velocity components along the line between the centers are swapped
+    /// and the balls are moved apart. Returns whether they collided; balls
+    /// already moving apart are left alone.
+    pub fn collide_with(&mut self, other: &mut Ball, radius: f32) -> bool {
+        let (dx, dy) = (other.x - self.x, other.y - self.y);
+        let distance = dx.hypot(dy);
+        if distance >= 2.0 * radius || distance == 0.0 {
+            return false;
+        }
+        let (normal_x, normal_y) = (dx / distance, dy / distance);
+        let closing = (self.velocity_x - other.velocity_x) * normal_x
+            + (self.velocity_y - other.velocity_y) * normal_y;
+        if closing <= 0.0 {
+            return false;
+        }
+        self.velocity_x -= closing * normal_x;
+        self.velocity_y -= closing * normal_y;
+        other.velocity_x += closing * normal_x;
+        other.velocity_y += closing * normal_y;
+
+        let overlap = (2.0 * radius - distance) / 2.0;
+        self.x -= overlap * normal_x;
+        self.y -= overlap * normal_y;
+        other.x += overlap * normal_x;
+        other.y += overlap * normal_y;
+        true
+    }
+
+    /// Moves the ball just clear of `block` on the chosen axes, out through
+    /// whichever side it has sunk into least, so it can't still be inside
+    /// the block on the next step.
+    pub fn push_out_of(&mut self, block: &Block, radius: f32, along_x: bool, along_y: bool) {
+        if along_x {
+            if self.x + radius - block.rect_x1 < block.rect_x2 - (self.x - radius) {
+                self.x = block.rect_x1 - radius;
+            } else {
+                self.x = block.rect_x2 + radius;
+            }
+        }
+        if along_y {
+            if self.y + radius - block.rect_y1 < block.rect_y2 - (self.y - radius) {
+                self.y = block.rect_y1 - radius;
+            } else {
+                self.y = block.rect_y2 + radius;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn head_on_collision_swaps_velocities() {
+        let mut left = Ball::new(100.0, 100.0, 200.0, 0.0);
+        let mut right = Ball::new(125.0, 100.0, -100.0, 0.0);
+        assert!(left.collide_with(&mut right, 15.0));
+        assert_eq!((left.velocity_x, left.velocity_y), (-100.0, 0.0));
+        assert_eq!((right.velocity_x, right.velocity_y), (200.0, 0.0));
+        // Pushed apart to just touching
+        assert_eq!(right.x - left.x, 30.0);
+    }
+}
@@ -0,0 +1,288 @@
+use ggez::graphics::Color;
+use rust_decimal::prelude::*;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Colors of successive grid rows from the top, repeating after the last
+pub const ROW_COLORS: [Color; 5] = [
+    Color::new(1.0, 0.0, 0.0, 1.0),
+    Color::new(1.0, 0.55, 0.0, 1.0),
+    Color::new(1.0, 0.85, 0.0, 1.0),
+    Color::new(0.0, 0.8, 0.0, 1.0),
+    Color::new(0.0, 0.4, 1.0, 1.0),
+];
+
+pub fn row_color(row: u32) -> Color {
+    ROW_COLORS[row as usize % ROW_COLORS.len()]
+}
+
+/// Color of indestructible blocks
+pub const SOLID_COLOR: Color = Color::new(0.5, 0.5, 0.5, 1.0);
+
+/// Whether a block can be broken at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockKind {
+    #[default]
+    Normal,
+    /// Reflects the ball but never breaks and isn't needed to clear a level
+    Indestructible,
+    /// A big block roaming the top of the field; breaking it clears the
+    /// level, whatever else is left standing
+    Boss,
+}
+
+/// Size of a boss block, the hits it takes and the points it scores
+pub const BOSS_WIDTH: f32 = 160.0;
+pub const BOSS_HEIGHT: f32 = 50.0;
+pub const BOSS_HITS: u32 = 20;
+const BOSS_POINTS: u32 = 500;
+/// How fast a boss roams sideways, and how far it drops each time it turns
+const BOSS_SPEED: f32 = 150.0;
+const BOSS_DROP: f32 = 20.0;
+/// Boss color at full health, fading to `ROW_COLORS[0]` as it takes hits
+const BOSS_COLOR: Color = Color::new(0.5, 0.0, 0.5, 1.0);
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Color")]
+pub(crate) struct ColorDef {
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Block {
+    pub rect_x1: f32,
+    pub rect_y1: f32,
+    pub rect_x2: f32,
+    pub rect_y2: f32,
+    pub is_visible: bool, 
+    pub points: u32,
+    pub hits_remaining: u32,
+    /// Grid cell the block was laid out in
+    pub row: u32,
+    pub col: u32,
+    #[serde(with = "ColorDef")]
+    pub color: Color,
+    pub kind: BlockKind,
+    /// Sideways speed in pixels per second; 0 keeps the block in place
+    pub velocity_x: f32,
+    /// Hits the block took to break when it was laid out
+    #[serde(default)]
+    pub max_hits: u32,
+}
+
+impl Block {
+    pub fn new(x1: f32, y1: f32, width: f32, height: f32, points: u32, hits: u32) -> Self {
+        Block {
+            rect_x1: x1,
+            rect_y1: y1,
+            rect_x2: x1 + width,
+            rect_y2: y1 + height,
+            is_visible: true,
+            points,
+            hits_remaining: hits,
+            row: 0,
+            col: 0,
+            color: Color::GREEN,
+            kind: BlockKind::Normal,
+            velocity_x: 0.0,
+            max_hits: hits,
+        }
+    }
+
+    /// A boss block with its top-left corner at (`x`, `y`), heading right.
+    pub fn boss(x: f32, y: f32) -> Self {
+        Block {
+            color: BOSS_COLOR,
+            kind: BlockKind::Boss,
+            velocity_x: BOSS_SPEED,
+            ..Block::new(x, y, BOSS_WIDTH, BOSS_HEIGHT, BOSS_POINTS, BOSS_HITS)
+        }
+    }
+
+    /// Share of its hits the block still has to take, from 1 down to 0.
+    pub fn health(&self) -> f32 {
+        self.hits_remaining as f32 / self.max_hits.max(1) as f32
+    }
+
+    /// Slides a moving block along, turning it around at the field edges.
+    pub fn update_position(&mut self, field_width: f32, dt: f32) {
+        let width = self.rect_x2 - self.rect_x1;
+        let mut x = self.rect_x1 + self.velocity_x * dt;
+        if x <= 0.0 {
+            x = 0.0;
+            self.velocity_x = self.velocity_x.abs();
+        } else if x + width >= field_width {
+            x = field_width - width;
+            self.velocity_x = -self.velocity_x.abs();
+        }
+        self.rect_x1 = x;
+        self.rect_x2 = x + width;
+    }
+
+    /// Moves a boss along like any moving block, dropping `BOSS_DROP` each
+    /// time it turns at a side, and going back up to `top` instead once
+    /// that would take its bottom below `lowest`.
+    pub fn update_boss_position(&mut self, field_width: f32, (top, lowest): (f32, f32), dt: f32) {
+        let heading = self.velocity_x;
+        self.update_position(field_width, dt);
+        if self.velocity_x.signum() == heading.signum() {
+            return;
+        }
+        let height = self.rect_y2 - self.rect_y1;
+        let mut y = self.rect_y1 + BOSS_DROP;
+        if y + height > lowest {
+            y = top;
+        }
+        self.rect_y1 = y;
+        self.rect_y2 = y + height;
+    }
+
+    pub fn is_breakable(&self) -> bool {
+        self.kind != BlockKind::Indestructible
+    }
+
+    pub(crate) fn decimal_rect(&self) -> Option<(Decimal, Decimal, Decimal, Decimal)> {
+        Some((
+            Decimal::from_f32(self.rect_x1)?,
+            Decimal::from_f32(self.rect_y1)?,
+            Decimal::from_f32(self.rect_x2)?,
+            Decimal::from_f32(self.rect_y2)?,
+        ))
+    }
+}
+
+/// A full `rows` x `cols` grid of one-hit blocks, `spacing` apart, with the
+/// top-left block's corner at `origin`.
+pub fn grid_layout(
+    rows: u32,
+    cols: u32,
+    block_width: f32,
+    block_height: f32,
+    spacing: f32,
+    (origin_x, origin_y): (f32, f32),
+) -> Vec<Block> {
+    let mut blocks = Vec::new();
+
+    for row in 0..rows {
+        for col in 0..cols {
+            let x = origin_x + col as f32 * (block_width + spacing);
+            let y = origin_y + row as f32 * (block_height + spacing);
+            // Top row is worth the most: 50, 40, ..., 10 for the bottom row of five
+            let points = (rows - row) * 10;
+            blocks.push(Block {
+                row,
+                col,
+                color: row_color(row),
+                ..Block::new(x, y, block_width, block_height, points, 1)
+            });
+        }
+    }
+    blocks
+}
+
+/// Parses an ASCII grid where '#' is a block, a digit 1-9 is a block taking
+/// that many hits, 'X' is an indestructible block, 'B' is a boss with its
+/// top-left corner in that cell and '.' is an empty cell. Cells are laid
+/// out as in `grid_layout`, `block_width` by `block_height` and `spacing`
+/// apart from `origin`.
+pub fn parse_layout(
+    layout: &str,
+    block_width: f32,
+    block_height: f32,
+    spacing: f32,
+    (origin_x, origin_y): (f32, f32),
+) -> Result<Vec<Block>, String> {
+    let lines: Vec<&str> = layout.lines().map(|line| line.trim_end()).collect();
+    let rows = lines.len() as u32;
+    let cols = lines.first().map_or(0, |line| line.chars().count());
+
+    let mut blocks = Vec::new();
+    for (row, line) in lines.iter().enumerate() {
+        if line.chars().count() != cols {
+            return Err(format!(
+                "row {} has {} cells, expected {}",
+                row + 1,
+                line.chars().count(),
+                cols
+            ));
+        }
+        for (col, cell) in line.chars().enumerate() {
+            let x = origin_x + col as f32 * (block_width + spacing);
+            let y = origin_y + row as f32 * (block_height + spacing);
+            if cell == 'X' {
+                blocks.push(Block {
+                    row: row as u32,
+                    col: col as u32,
+                    color: SOLID_COLOR,
+                    kind: BlockKind::Indestructible,
+                    ..Block::new(x, y, block_width, block_height, 0, 1)
+                });
+                continue;
+            }
+            if cell == 'B' {
+                blocks.push(Block {
+                    row: row as u32,
+                    col: col as u32,
+                    ..Block::boss(x, y)
+                });
+                continue;
+            }
+            let hits = match cell {
+                '#' => 1,
+                '1'..='9' => cell.to_digit(10).unwrap_or(1),
+                '.' => continue,
+                other => {
+                    return Err(format!(
+                        "unexpected character '{}' at row {}, column {}",
+                        other,
+                        row + 1,
+                        col + 1
+                    ))
+                }
+            };
+            let points = (rows - row as u32) * 10;
+            blocks.push(Block {
+                row: row as u32,
+                col: col as u32,
+                color: row_color(row as u32),
+                ..Block::new(x, y, block_width, block_height, points, hits)
+            });
+        }
+    }
+    if blocks.is_empty() {
+        return Err("layout contains no blocks".to_string());
+    }
+    Ok(blocks)
+}
+
+/// Writes the standing blocks as a grid `parse_layout` reads back, each in
+/// the cell given by its row and column.
+pub fn format_layout(blocks: &[Block]) -> String {
+    let standing: Vec<&Block> = blocks.iter().filter(|block| block.is_visible).collect();
+    let rows = standing
+        .iter()
+        .map(|block| block.row + 1)
+        .max()
+        .unwrap_or(0);
+    let cols = standing
+        .iter()
+        .map(|block| block.col + 1)
+        .max()
+        .unwrap_or(0);
+    let mut cells = vec![vec!['.'; cols as usize]; rows as usize];
+    for block in standing {
+        cells[block.row as usize][block.col as usize] = match block.kind {
+            BlockKind::Indestructible => 'X',
+            BlockKind::Boss => 'B',
+            BlockKind::Normal if block.hits_remaining <= 1 => '#',
+            BlockKind::Normal => char::from_digit(block.hits_remaining.min(9), 10).unwrap_or('9'),
+        };
+    }
+    cells
+        .into_iter()
+        .map(|row| row.into_iter().collect::<String>() + "\n")
+        .collect()
+}
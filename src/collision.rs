@@ -0,0 +1,222 @@
+use rust_decimal::Decimal;
+
+pub fn check_circle_rectangle_collision(
+    circle_x: Decimal,
+    circle_y: Decimal,
+    radius: Decimal,
+    rect_x1: Decimal,
+    rect_y1: Decimal,
+    rect_x2: Decimal,
+    rect_y2: Decimal,
+) -> Option<(bool, bool)> {
+    let nearest_x = rect_x1.max(circle_x.min(rect_x2));
+    let nearest_y = rect_y1.max(circle_y.min(rect_y2));
+
+    let distance_x = circle_x - nearest_x;
+    let distance_y = circle_y - nearest_y;
+    let distance_squared = distance_x * distance_x + distance_y * distance_y;
+    let radius_squared = radius * radius;
+
+    if distance_squared <= radius_squared {
+        // Reflect on the axis the ball has sunk into least, which is the side
+        // it came in through; only an exact tie is treated as a corner strike
+        let penetration_x = (circle_x + radius - rect_x1).min(rect_x2 - (circle_x - radius));
+        let penetration_y = (circle_y + radius - rect_y1).min(rect_y2 - (circle_y - radius));
+        Some((
+            penetration_x <= penetration_y,
+            penetration_y <= penetration_x,
+        ))
+    } else {
+        None
+    }
+}
+
+/// Same as `check_circle_rectangle_collision`, using plain f32 arithmetic.
+/// Any NaN coordinate yields `None`.
+pub fn check_circle_rectangle_collision_f32(
+    circle_x: f32,
+    circle_y: f32,
+    radius: f32,
+    rect_x1: f32,
+    rect_y1: f32,
+    rect_x2: f32,
+    rect_y2: f32,
+) -> Option<(bool, bool)> {
+    let nearest_x = rect_x1.max(circle_x.min(rect_x2));
+    let nearest_y = rect_y1.max(circle_y.min(rect_y2));
+
+    let distance_x = circle_x - nearest_x;
+    let distance_y = circle_y - nearest_y;
+    let distance_squared = distance_x * distance_x + distance_y * distance_y;
+    let radius_squared = radius * radius;
+
+    if distance_squared <= radius_squared {
+        let penetration_x = (circle_x + radius - rect_x1).min(rect_x2 - (circle_x - radius));
+        let penetration_y = (circle_y + radius - rect_y1).min(rect_y2 - (circle_y - radius));
+        Some((
+            penetration_x <= penetration_y,
+            penetration_y <= penetration_x,
+        ))
+    } else {
+        None
+    }
+}
+
+/// Where a circle overlaps a rectangle: the unit normal pointing out of the
+/// rectangle toward the circle, and how far the circle has to move along it
+/// to stop overlapping.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    pub normal: (f32, f32),
+    pub depth: f32,
+}
+
+/// Like `check_circle_rectangle_collision_f32`, but with the rectangle as
+/// (x1, y1, x2, y2) and giving the actual `Contact`, so a corner strike
+/// has a diagonal normal. A circle whose center is inside the rectangle is
+/// sent out through the nearest face. Any NaN coordinate yields `None`.
+pub fn circle_rectangle_contact(
+    circle_x: f32,
+    circle_y: f32,
+    radius: f32,
+    rect: (f32, f32, f32, f32),
+) -> Option<Contact> {
+    let nearest_x = rect.0.max(circle_x.min(rect.2));
+    let nearest_y = rect.1.max(circle_y.min(rect.3));
+    let (distance_x, distance_y) = (circle_x - nearest_x, circle_y - nearest_y);
+    let distance = distance_x.hypot(distance_y);
+    if distance.is_nan() || distance > radius {
+        return None;
+    }
+    if distance > 0.0 {
+        return Some(Contact {
+            normal: (distance_x / distance, distance_y / distance),
+            depth: radius - distance,
+        });
+    }
+    let faces = [
+        (circle_x - rect.0, (-1.0, 0.0)),
+        (rect.2 - circle_x, (1.0, 0.0)),
+        (circle_y - rect.1, (0.0, -1.0)),
+        (rect.3 - circle_y, (0.0, 1.0)),
+    ];
+    let (gap, normal) = faces.into_iter().min_by(|a, b| a.0.total_cmp(&b.0))?;
+    Some(Contact {
+        normal,
+        depth: gap + radius,
+    })
+}
+
+/// Whether two rectangles given as (x1, y1, x2, y2) overlap. Rectangles
+/// that only touch along an edge don't count.
+pub fn rect_intersects_rect(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) -> bool {
+    a.0 < b.2 && b.0 < a.2 && a.1 < b.3 && b.1 < a.3
+}
+
+/// Cheap broad phase for the circle-rectangle tests: whether the circle's
+/// bounding box overlaps the rectangle. Touching counts, as it does for a
+/// circle resting exactly on an edge. Any NaN coordinate yields `false`.
+pub fn circle_bounds_overlap_rect(
+    circle_x: f32,
+    circle_y: f32,
+    radius: f32,
+    rect: (f32, f32, f32, f32),
+) -> bool {
+    circle_x + radius >= rect.0
+        && circle_x - radius <= rect.2
+        && circle_y + radius >= rect.1
+        && circle_y - radius <= rect.3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::prelude::FromPrimitive;
+
+    const RECT: (f32, f32, f32, f32) = (100.0, 100.0, 130.0, 130.0);
+
+    fn exact(circle_x: f32, circle_y: f32, radius: f32) -> Option<(bool, bool)> {
+        let decimal = |value: f32| Decimal::from_f32(value).unwrap();
+        check_circle_rectangle_collision(
+            decimal(circle_x),
+            decimal(circle_y),
+            decimal(radius),
+            decimal(RECT.0),
+            decimal(RECT.1),
+            decimal(RECT.2),
+            decimal(RECT.3),
+        )
+    }
+
+    fn fast(circle_x: f32, circle_y: f32, radius: f32) -> Option<(bool, bool)> {
+        check_circle_rectangle_collision_f32(
+            circle_x, circle_y, radius, RECT.0, RECT.1, RECT.2, RECT.3,
+        )
+    }
+
+    #[test]
+    fn f32_and_decimal_collisions_agree() {
+        for radius in [5.0, 15.0, 20.5] {
+            for i in 0..=40 {
+                for j in 0..=40 {
+                    let (x, y) = (70.0 + i as f32 * 2.25, 70.0 + j as f32 * 2.25);
+                    assert_eq!(fast(x, y, radius), exact(x, y, radius), "at ({}, {})", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn top_edge_hit_reflects_vertically() {
+        assert_eq!(exact(115.0, 88.0, 15.0), Some((false, true)));
+        assert_eq!(fast(115.0, 88.0, 15.0), Some((false, true)));
+    }
+
+    #[test]
+    fn side_edge_hit_reflects_horizontally() {
+        assert_eq!(exact(88.0, 115.0, 15.0), Some((true, false)));
+        assert_eq!(fast(142.0, 115.0, 15.0), Some((true, false)));
+    }
+
+    #[test]
+    fn corner_hit_reflects_only_on_the_shallower_axis() {
+        // Sunk in equally on both axes: a true corner strike
+        assert_eq!(exact(90.0, 90.0, 15.0), Some((true, true)));
+        // Near a corner but sunk in further on one axis than the other
+        assert_eq!(exact(88.0, 95.0, 15.0), Some((true, false)));
+        assert_eq!(fast(95.0, 88.0, 15.0), Some((false, true)));
+    }
+
+    #[test]
+    fn contact_normals_point_out_of_each_face() {
+        // Circle centers 10 pixels out from the middle of each face
+        let faces = [
+            ((115.0, 90.0), (0.0, -1.0)),
+            ((115.0, 140.0), (0.0, 1.0)),
+            ((90.0, 115.0), (-1.0, 0.0)),
+            ((140.0, 115.0), (1.0, 0.0)),
+        ];
+        for ((x, y), normal) in faces {
+            let contact = circle_rectangle_contact(x, y, 15.0, RECT).unwrap();
+            assert_eq!(contact.normal, normal, "at ({}, {})", x, y);
+            assert_eq!(contact.depth, 5.0);
+        }
+        assert_eq!(circle_rectangle_contact(115.0, 80.0, 15.0, RECT), None);
+    }
+
+    #[test]
+    fn corner_contact_has_a_diagonal_normal() {
+        let contact = circle_rectangle_contact(92.0, 92.0, 15.0, RECT).unwrap();
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((contact.normal.0 + half).abs() < 1e-6);
+        assert!((contact.normal.1 + half).abs() < 1e-6);
+        assert!((contact.depth - (15.0 - 8.0 * std::f32::consts::SQRT_2)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn center_inside_leaves_through_the_nearest_face() {
+        let contact = circle_rectangle_contact(127.0, 110.0, 15.0, RECT).unwrap();
+        assert_eq!(contact.normal, (1.0, 0.0));
+        assert_eq!(contact.depth, 18.0);
+    }
+}
@@ -0,0 +1,224 @@
+use std::time::Duration;
+
+use ggez::graphics::Color;
+use ggez::{GameError, GameResult};
+use serde::{Deserialize, Serialize};
+
+use crate::block::ColorDef;
+use crate::palette::Palette;
+
+/// Every tunable of a game, in pixels and pixels per second. The default
+/// matches the original hardcoded setup.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameConfig {
+    pub width: f32,
+    pub height: f32,
+    pub lives: u32,
+    pub ball_radius: f32,
+    /// Speed the ball is served at on the first level
+    pub launch_speed: f32,
+    pub max_speed: f32,
+    /// Share of the speed into a wall or block kept by bouncing off it; 1
+    /// loses nothing
+    pub restitution: f32,
+    /// Bounce off blocks along the true normal from `circle_rectangle_contact`
+    /// instead of flipping whole axes; ignores `MainState::exact_collisions`
+    pub normal_reflection: bool,
+    /// Treat the paddle as a plain rectangle, bouncing the ball off its
+    /// sides too, instead of a flat top with rounded ends
+    pub paddle_rect_collision: bool,
+    /// Slowest a ball losing speed to bounces is allowed to get
+    pub min_speed: f32,
+    /// Largest random turn, in degrees either way, given to a ball bouncing
+    /// off a wall so it can't settle into a loop; drawn from the game's
+    /// seed, and 0 turns it off
+    pub wall_jitter: f32,
+    /// SlowBall and FastBall scale the balls' speed by these while they last
+    pub slow_ball_factor: f32,
+    pub fast_ball_factor: f32,
+    /// Most balls a Split can bring into play at once
+    pub max_balls: usize,
+    pub paddle_width: f32,
+    pub paddle_height: f32,
+    pub paddle_speed: f32,
+    /// Controllers that chase the ball move the paddle at up to this many
+    /// times the ball's speed when it outpaces `paddle_speed`, capped at
+    /// `max_ai_paddle_speed`
+    pub ai_speed_factor: f32,
+    pub max_ai_paddle_speed: f32,
+    /// Grid of the first level; each later level adds one row
+    pub rows: u32,
+    pub cols: u32,
+    pub block_width: f32,
+    pub block_height: f32,
+    /// Preferred gap between neighbouring blocks, narrowed by
+    /// `fitted_spacing` when the row would not fit the field otherwise
+    pub block_spacing: f32,
+    /// Room above the block grid, which is centered across the field
+    pub top_margin: f32,
+    /// Chance in `[0, 1]` that a destroyed block drops a power-up
+    pub drop_chance: f32,
+    /// Fraction the ball speeds up by every `speed_ramp_interval` seconds
+    /// in play, up to `max_speed`; 0 turns the ramp off
+    pub speed_ramp: f32,
+    pub speed_ramp_interval: f32,
+    /// A ball whose vertical speed stays below `stall_speed` for
+    /// `stall_ticks` ticks in a row is given `stall_nudge` of vertical
+    /// speed, so it can't bounce between the side walls forever, and one
+    /// whose horizontal speed does is given as much sideways, so it can't
+    /// go up and down one column forever; 0 ticks turns this off
+    pub stall_speed: f32,
+    pub stall_ticks: u32,
+    pub stall_nudge: f32,
+    /// Downward acceleration of the ball in pixels per second squared
+    pub gravity: f32,
+    /// Play time allowed to clear every level before the game is lost
+    pub time_limit: Option<Duration>,
+    /// Round every position and velocity to `QUANTIZE_PLACES` decimal places
+    /// after each tick. Tiny float differences between platforms then get
+    /// rounded away instead of growing, so a replay recorded on one machine
+    /// plays out the same on another unless a value lands right on a
+    /// rounding boundary. Within one build a seed always replays exactly,
+    /// with or without this.
+    pub quantize: bool,
+    /// Ticks of past ball positions kept for drawing a trail
+    pub trail_length: usize,
+    #[serde(with = "ColorDef")]
+    pub background: Color,
+    /// Colors for the paddle, ball and blocks, and the background unless
+    /// it is `Palette::Classic`
+    pub palette: Palette,
+    /// Let balls bounce off each other, checking every pair each tick
+    pub ball_collisions: bool,
+    /// Keep a `CollisionRecord` of every bounce in `MainState::collisions`
+    pub log_collisions: bool,
+    /// Seconds the ball is held on the paddle after a life is lost before
+    /// it can be served
+    pub respawn_delay: f32,
+    /// Bounce sounds play at the ball's speed over `launch_speed` times
+    /// their normal pitch, kept within these; both 1 keeps it fixed
+    pub min_bounce_pitch: f32,
+    pub max_bounce_pitch: f32,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            width: 800.0,
+            height: 600.0,
+            lives: 3,
+            ball_radius: 15.0,
+            launch_speed: 180.0 * std::f32::consts::SQRT_2,
+            max_speed: 600.0,
+            restitution: 1.0,
+            normal_reflection: false,
+            paddle_rect_collision: false,
+            min_speed: 100.0,
+            wall_jitter: 0.0,
+            slow_ball_factor: 0.7,
+            fast_ball_factor: 1.3,
+            max_balls: 8,
+            paddle_width: 80.0,
+            paddle_height: 10.0,
+            paddle_speed: 300.0,
+            ai_speed_factor: 1.0,
+            max_ai_paddle_speed: 600.0,
+            rows: 5,
+            cols: 10,
+            block_width: 30.0,
+            block_height: 30.0,
+            block_spacing: 5.0,
+            top_margin: 50.0,
+            drop_chance: 0.2,
+            speed_ramp: 0.0,
+            speed_ramp_interval: 10.0,
+            stall_speed: 20.0,
+            stall_ticks: 120,
+            stall_nudge: 60.0,
+            gravity: 0.0,
+            time_limit: None,
+            quantize: false,
+            trail_length: 8,
+            background: Color::WHITE,
+            palette: Palette::Classic,
+            ball_collisions: false,
+            log_collisions: false,
+            respawn_delay: 1.5,
+            min_bounce_pitch: 0.8,
+            max_bounce_pitch: 1.6,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Checks that the block grid can be laid out in the field, with at
+    /// least one row and column and columns that fit the width even with no
+    /// gap, that there is a life to play and the ball fits, and that
+    /// bounces don't add speed or stop the ball dead, and that the bounce
+    /// pitch range is positive.
+    pub fn validate(&self) -> GameResult {
+        if self.rows == 0 || self.cols == 0 {
+            return Err(GameError::ConfigError(format!(
+                "A {}x{} block grid has no blocks",
+                self.rows, self.cols
+            )));
+        }
+        let row_width = self.cols as f32 * self.block_width;
+        if row_width > self.width {
+            return Err(GameError::ConfigError(format!(
+                "{} columns of {}px blocks need {}px, but the field is {}px wide",
+                self.cols, self.block_width, row_width, self.width
+            )));
+        }
+        if self.lives == 0 {
+            return Err(GameError::ConfigError(
+                "A game needs at least one life".to_string(),
+            ));
+        }
+        // Balls move in sub-steps of at most a radius, so it can't be zero
+        let largest_radius = self.width.min(self.height) / 2.0;
+        if !(self.ball_radius > 0.0 && self.ball_radius < largest_radius) {
+            return Err(GameError::ConfigError(format!(
+                "A ball radius of {}px doesn't fit a {}x{} field",
+                self.ball_radius, self.width, self.height
+            )));
+        }
+        if !(self.restitution > 0.0 && self.restitution <= 1.0) {
+            return Err(GameError::ConfigError(format!(
+                "Restitution {} is outside 0-1",
+                self.restitution
+            )));
+        }
+        if !(self.min_bounce_pitch > 0.0 && self.min_bounce_pitch <= self.max_bounce_pitch) {
+            return Err(GameError::ConfigError(format!(
+                "Bounce pitch range {}-{} is empty or not positive",
+                self.min_bounce_pitch, self.max_bounce_pitch
+            )));
+        }
+        Ok(())
+    }
+
+    /// Pitch ratio for a bounce sound from a ball moving at `speed`.
+    pub fn bounce_pitch(&self, speed: f32) -> f32 {
+        (speed / self.launch_speed).clamp(self.min_bounce_pitch, self.max_bounce_pitch)
+    }
+
+    /// Top-left corner of the block grid: centered across the field, with
+    /// `top_margin` above it.
+    pub fn grid_origin(&self) -> (f32, f32) {
+        let gap = self.fitted_spacing();
+        let grid_width =
+            self.cols as f32 * self.block_width + self.cols.saturating_sub(1) as f32 * gap;
+        (((self.width - grid_width) / 2.0).max(0.0), self.top_margin)
+    }
+
+    /// `block_spacing`, or the widest gap that still fits `cols` blocks
+    /// across the field if that is smaller.
+    pub fn fitted_spacing(&self) -> f32 {
+        if self.cols < 2 {
+            return self.block_spacing;
+        }
+        let room = (self.width - self.cols as f32 * self.block_width) / (self.cols - 1) as f32;
+        self.block_spacing.min(room).max(0.0)
+    }
+}
@@ -0,0 +1,255 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ball::Ball;
+use crate::block::Block;
+use crate::paddle::{
+    predict_landing_x, predict_path, Paddle, PaddleAction, PaddleController, MAX_BOUNCE_ANGLE,
+};
+
+/// Furthest off the paddle's center `GameView::aim_point` lands the ball,
+/// as a share of its half width, keeping clear of the rounded ends
+const AIM_LIMIT: f32 = 0.8;
+
+/// Read-only look at the game a `Controller` decides from.
+pub struct GameView<'a> {
+    pub balls: &'a [Ball],
+    pub paddle: &'a Paddle,
+    pub blocks: &'a [Block],
+    pub width: f32,
+    pub radius: f32,
+    /// Direction the player is holding: -1 left, 0 none, 1 right
+    pub manual_direction: f32,
+    /// Seconds the coming tick covers
+    pub dt: f32,
+}
+
+impl GameView<'_> {
+    /// The ball worth reacting to: the lowest one that is coming down, or
+    /// the first ball if none are.
+    pub fn tracked_ball(&self) -> Option<&Ball> {
+        self.balls
+            .iter()
+            .filter(|ball| ball.velocity_y > 0.0)
+            .max_by(|a, b| a.y.total_cmp(&b.y))
+            .or_else(|| self.balls.first())
+    }
+
+    /// Where `ball`'s center will cross the paddle line. The prediction runs
+    /// with the center confined to the walls and paddle line it actually
+    /// bounces between.
+    pub fn predicted_landing_x(&self, ball: &Ball) -> f32 {
+        predict_landing_x(
+            ball.x - self.radius,
+            ball.y - self.radius,
+            ball.velocity_x,
+            ball.velocity_y,
+            self.paddle.y - 2.0 * self.radius,
+            self.width - 2.0 * self.radius,
+        ) + self.radius
+    }
+
+    /// Where to put the paddle's center for a ball landing at `x`, so its
+    /// `Paddle::bounce_angle` sends it at the lowest block standing in the
+    /// column with the most left. Just `x` once no blocks are left.
+    pub fn aim_point(&self, x: f32) -> f32 {
+        let Some((target_x, target_y)) = self.aim_target() else {
+            return x;
+        };
+        let angle = (target_x - x).atan2(self.paddle.y - target_y).to_degrees();
+        let offset = (angle / MAX_BOUNCE_ANGLE).clamp(-AIM_LIMIT, AIM_LIMIT);
+        x - offset * self.paddle.width / 2.0
+    }
+
+    /// The middle of the bottom of the lowest breakable block in the column
+    /// with the most of them standing, the leftmost of those tied.
+    fn aim_target(&self) -> Option<(f32, f32)> {
+        let mut columns: BTreeMap<u32, (usize, &Block)> = BTreeMap::new();
+        for block in self.blocks {
+            if !block.is_visible || !block.is_breakable() {
+                continue;
+            }
+            let column = columns.entry(block.col).or_insert((0, block));
+            column.0 += 1;
+            if block.rect_y2 > column.1.rect_y2 {
+                column.1 = block;
+            }
+        }
+        let (_, lowest) = columns
+            .into_values()
+            .min_by_key(|&(count, _)| Reverse(count))?;
+        Some(((lowest.rect_x1 + lowest.rect_x2) / 2.0, lowest.rect_y2))
+    }
+
+    /// The path of `ball`'s center up to the paddle line that
+    /// `predicted_landing_x` assumes, as at most `max_segments` segments.
+    pub fn predicted_path(&self, ball: &Ball, max_segments: usize) -> Vec<(f32, f32)> {
+        predict_path(
+            ball.x - self.radius,
+            ball.y - self.radius,
+            ball.velocity_x,
+            ball.velocity_y,
+            self.paddle.y - 2.0 * self.radius,
+            self.width - 2.0 * self.radius,
+            max_segments,
+        )
+        .into_iter()
+        .map(|(x, y)| (x + self.radius, y + self.radius))
+        .collect()
+    }
+}
+
+/// Drives the paddle, asked once per tick.
+pub trait Controller {
+    fn decide(&mut self, view: &GameView) -> PaddleAction;
+
+    /// Whether a waiting ball is served straight away; otherwise the game
+    /// waits for `MainState::serve`.
+    fn serves_automatically(&self) -> bool {
+        true
+    }
+
+    /// Whether the paddle may speed up past `GameConfig::paddle_speed` to
+    /// keep up with a fast ball.
+    fn adapts_paddle_speed(&self) -> bool {
+        false
+    }
+
+    /// Which built-in controller this is and the state it has, for
+    /// `MainState::to_json`; `None` for one that can't be saved.
+    fn save(&self) -> Option<SavedController> {
+        None
+    }
+}
+
+/// A built-in controller as kept in a snapshot, with any state it carries
+/// from tick to tick.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SavedController {
+    Bounce { direction: f32 },
+    FollowBall,
+    Predictive,
+    Manual,
+}
+
+impl SavedController {
+    /// The controller as it was when saved.
+    pub fn restore(self) -> Box<dyn Controller> {
+        match self {
+            SavedController::Bounce { direction } => Box::new(BounceController { direction }),
+            SavedController::FollowBall => Box::new(FollowController),
+            SavedController::Predictive => Box::new(PredictiveController),
+            SavedController::Manual => Box::new(ManualController),
+        }
+    }
+}
+
+/// Sweeps left and right between the walls, ignoring the ball.
+pub struct BounceController {
+    /// -1 heading left, 1 heading right
+    pub direction: f32,
+}
+
+impl Controller for BounceController {
+    fn decide(&mut self, view: &GameView) -> PaddleAction {
+        if view.paddle.x <= 0.0 {
+            self.direction = 1.0;
+        } else if view.paddle.x + view.paddle.width >= view.width {
+            self.direction = -1.0;
+        }
+        if self.direction < 0.0 {
+            PaddleAction::MoveLeft
+        } else {
+            PaddleAction::MoveRight
+        }
+    }
+
+    fn save(&self) -> Option<SavedController> {
+        Some(SavedController::Bounce {
+            direction: self.direction,
+        })
+    }
+}
+
+/// Chases the ball while it is descending, meeting it at `GameView::aim_point`.
+pub struct FollowController;
+
+impl Controller for FollowController {
+    fn decide(&mut self, view: &GameView) -> PaddleAction {
+        match view.tracked_ball() {
+            Some(ball) if ball.velocity_y > 0.0 => {
+                view.paddle.action_toward(view.aim_point(ball.x), view.dt)
+            }
+            _ => PaddleAction::Stay,
+        }
+    }
+
+    fn adapts_paddle_speed(&self) -> bool {
+        true
+    }
+
+    fn save(&self) -> Option<SavedController> {
+        Some(SavedController::FollowBall)
+    }
+}
+
+/// Moves ahead of time to where the ball will cross the paddle line, lined
+/// up to meet it at `GameView::aim_point`.
+pub struct PredictiveController;
+
+impl Controller for PredictiveController {
+    fn decide(&mut self, view: &GameView) -> PaddleAction {
+        match view.tracked_ball() {
+            Some(ball) => {
+                let target = view.aim_point(view.predicted_landing_x(ball));
+                view.paddle.action_toward(target, view.dt)
+            }
+            None => PaddleAction::Stay,
+        }
+    }
+
+    fn adapts_paddle_speed(&self) -> bool {
+        true
+    }
+
+    fn save(&self) -> Option<SavedController> {
+        Some(SavedController::Predictive)
+    }
+}
+
+/// Follows the direction the player is holding and waits for them to serve.
+pub struct ManualController;
+
+impl Controller for ManualController {
+    fn decide(&mut self, view: &GameView) -> PaddleAction {
+        if view.manual_direction < 0.0 {
+            PaddleAction::MoveLeft
+        } else if view.manual_direction > 0.0 {
+            PaddleAction::MoveRight
+        } else {
+            PaddleAction::Stay
+        }
+    }
+
+    fn serves_automatically(&self) -> bool {
+        false
+    }
+
+    fn save(&self) -> Option<SavedController> {
+        Some(SavedController::Manual)
+    }
+}
+
+impl PaddleController {
+    /// A fresh instance of the built-in controller this names.
+    pub fn build(self) -> Box<dyn Controller> {
+        match self {
+            PaddleController::Bounce => Box::new(BounceController { direction: 1.0 }),
+            PaddleController::FollowBall => Box::new(FollowController),
+            PaddleController::Predictive => Box::new(PredictiveController),
+            PaddleController::Manual => Box::new(ManualController),
+        }
+    }
+}
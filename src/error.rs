@@ -0,0 +1,43 @@
+use ggez::GameError;
+
+/// An invariant the simulation broke during a tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StepError {
+    /// A ball's position or velocity became NaN or infinite
+    NonFiniteBall {
+        x: f32,
+        y: f32,
+        velocity_x: f32,
+        velocity_y: f32,
+    },
+    /// A ball is fully past a wall or the ceiling and still moving away from the field
+    BallOutOfBounds { x: f32, y: f32 },
+}
+
+impl std::fmt::Display for StepError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            StepError::NonFiniteBall {
+                x,
+                y,
+                velocity_x,
+                velocity_y,
+            } => write!(
+                f,
+                "ball at ({}, {}) with velocity ({}, {}) is not finite",
+                x, y, velocity_x, velocity_y
+            ),
+            StepError::BallOutOfBounds { x, y } => {
+                write!(f, "ball at ({}, {}) escaped the field", x, y)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StepError {}
+
+impl From<StepError> for GameError {
+    fn from(error: StepError) -> Self {
+        GameError::CustomError(format!("Simulation error: {}", error))
+    }
+}
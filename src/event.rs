@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// Something that happened during a tick, collected on `MainState::events`
+/// along with the tick number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameEvent {
+    /// The block in this grid cell lost its last hit
+    BlockDestroyed {
+        row: u32,
+        col: u32,
+    },
+    PaddleHit,
+    WallBounce,
+    /// A ball fell off the bottom; a `LifeLost` follows if it was the last one
+    BallLost,
+    LifeLost,
+    LevelCleared,
+}
+
+/// What a ball bounced off, in a `CollisionRecord`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CollisionObject {
+    Wall,
+    Paddle,
+    Block,
+    Ball,
+}
+
+/// One bounce, kept in `MainState::collisions` when
+/// `GameConfig::log_collisions` is set.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CollisionRecord {
+    pub tick: u64,
+    pub object: CollisionObject,
+    /// Index into `MainState::blocks` for a block hit
+    pub block_index: Option<usize>,
+    /// Ball velocity as (x, y) before and after the bounce
+    pub velocity_before: (f32, f32),
+    pub velocity_after: (f32, f32),
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+
+use crate::error::StepError;
+use crate::event::GameEvent;
+use crate::state::{GameState, MainState};
+
+/// How a headless run ended, with the final score and the number of ticks simulated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameOutcome {
+    Won {
+        score: u32,
+        ticks: usize,
+    },
+    Lost {
+        score: u32,
+        ticks: usize,
+    },
+    /// `max_ticks` was reached with the game still in progress
+    TimedOut {
+        score: u32,
+        ticks: usize,
+    },
+}
+
+impl GameOutcome {
+    pub fn score(&self) -> u32 {
+        match *self {
+            GameOutcome::Won { score, .. }
+            | GameOutcome::Lost { score, .. }
+            | GameOutcome::TimedOut { score, .. } => score,
+        }
+    }
+
+    pub fn ticks(&self) -> usize {
+        match *self {
+            GameOutcome::Won { ticks, .. }
+            | GameOutcome::Lost { ticks, .. }
+            | GameOutcome::TimedOut { ticks, .. } => ticks,
+        }
+    }
+}
+
+/// Totals from a headless run, for comparing controllers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GameMetrics {
+    pub ticks: usize,
+    pub score: u32,
+    pub blocks_destroyed: u32,
+    pub paddle_hits: u32,
+    pub balls_lost: u32,
+    pub won: bool,
+    /// `MainState::current_tick` when the run stopped
+    pub end_tick: u64,
+}
+
+impl fmt::Display for GameMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} after {} ticks (at tick {}): score {}, {} blocks destroyed, {} paddle hits, {} balls lost",
+            if self.won { "won" } else { "not won" },
+            self.ticks,
+            self.end_tick,
+            self.score,
+            self.blocks_destroyed,
+            self.paddle_hits,
+            self.balls_lost
+        )
+    }
+}
+
+impl MainState {
+    /// Steps the simulation without a window until the game is won, lost,
+    /// or `max_ticks` physics ticks have run. Consumes the events it
+    /// counts.
+    pub fn run_headless(&mut self, max_ticks: usize) -> Result<GameMetrics, StepError> {
+        let mut metrics = GameMetrics::default();
+        while self.state.is_running() && metrics.ticks < max_ticks {
+            self.step()?;
+            metrics.ticks += 1;
+            for (_, event) in self.drain_events() {
+                match event {
+                    GameEvent::BlockDestroyed { .. } => metrics.blocks_destroyed += 1,
+                    GameEvent::PaddleHit => metrics.paddle_hits += 1,
+                    GameEvent::BallLost => metrics.balls_lost += 1,
+                    _ => {}
+                }
+            }
+        }
+        metrics.score = self.current_score();
+        metrics.won = self.state == GameState::Won;
+        metrics.end_tick = self.current_tick();
+        Ok(metrics)
+    }
+
+    pub(crate) fn outcome(&self, ticks: usize) -> GameOutcome {
+        let score = self.current_score();
+        match self.state {
+            GameState::Won => GameOutcome::Won { score, ticks },
+            GameState::GameOver => GameOutcome::Lost { score, ticks },
+            GameState::Menu | GameState::Editing | GameState::Serving | GameState::Playing => {
+                GameOutcome::TimedOut { score, ticks }
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+mod ball;
+mod block;
+mod collision;
+mod config;
+mod controller;
+mod error;
+mod event;
+mod headless;
+mod paddle;
+mod palette;
+mod particle;
+mod power_up;
+mod replay;
+mod rng;
+mod spatial;
+mod state;
+
+pub use ball::Ball;
+pub use block::{
+    format_layout, grid_layout, parse_layout, row_color, Block, BlockKind, BOSS_HEIGHT, BOSS_HITS,
+    BOSS_WIDTH, ROW_COLORS, SOLID_COLOR,
+};
+pub use collision::{
+    check_circle_rectangle_collision, check_circle_rectangle_collision_f32,
+    circle_bounds_overlap_rect, circle_rectangle_contact, rect_intersects_rect, Contact,
+};
+pub use config::GameConfig;
+pub use controller::{
+    BounceController, Controller, FollowController, GameView, ManualController,
+    PredictiveController, SavedController,
+};
+pub use error::StepError;
+pub use event::{CollisionObject, CollisionRecord, GameEvent};
+pub use headless::{GameMetrics, GameOutcome};
+pub use paddle::{
+    predict_landing_x, predict_path, Paddle, PaddleAction, PaddleController, MAX_BOUNCE_ANGLE,
+    MIN_BOUNCE_ANGLE,
+};
+pub use palette::Palette;
+pub use particle::{Particle, ScorePopup, PARTICLE_SIZE};
+pub use power_up::{Laser, PowerUp, PowerUpKind, LASER_HEIGHT, LASER_WIDTH, POWER_UP_SIZE};
+pub use replay::Replay;
+pub use rng::Rng;
+pub use spatial::{SpatialGrid, CELL_SIZE};
+pub use state::{
+    default_controller, BottomBehavior, GameState, MainState, FINAL_LEVEL, QUANTIZE_PLACES,
+    SHIELD_HEIGHT,
+};
+
+/// Length of one physics tick in seconds
+pub const TIME_STEP: f32 = 1.0 / 60.0;
@@ -1,226 +1,989 @@
-use ggez::event::{self, EventHandler};
-use ggez::graphics::{self, Color, DrawMode, Mesh};
-use ggez::mint::Point2;
-use ggez::{Context, GameResult};
-use rust_decimal::prelude::*;
-use rust_decimal::Decimal;
-
-fn check_circle_rectangle_collision(
-    circle_x: Decimal,
-    circle_y: Decimal,
-    radius: Decimal,
-    rect_x1: Decimal,
-    rect_y1: Decimal,
-    rect_x2: Decimal,
-    rect_y2: Decimal,
-) -> Option<(bool, bool)> {
-    let nearest_x = rect_x1.max(circle_x.min(rect_x2));
-    let nearest_y = rect_y1.max(circle_y.min(rect_y2));
-
-    let distance_x = circle_x - nearest_x;
-    let distance_y = circle_y - nearest_y;
-    let distance_squared = distance_x * distance_x + distance_y * distance_y;
-    let radius_squared = radius * radius;
-
-    if distance_squared <= radius_squared {
-        let collision_x = nearest_x == rect_x1 || nearest_x == rect_x2;
-        let collision_y = nearest_y == rect_y1 || nearest_y == rect_y2;
-        Some((collision_x, collision_y))
-    } else {
-        None
-    }
-}
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use arkanoid::{
+    Block, BlockKind, BottomBehavior, GameConfig, GameEvent, GameState, MainState,
+    PaddleController, Palette, PowerUpKind, Rng, LASER_HEIGHT, LASER_WIDTH, PARTICLE_SIZE,
+    POWER_UP_SIZE, TIME_STEP,
+};
+use ggez::audio::{self, SoundSource};
+use ggez::conf::{WindowMode, WindowSetup};
+use ggez::event::{self, EventHandler, KeyCode, KeyMods, MouseButton};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Mesh};
+use ggez::mint::{Point2, Vector2};
+use ggez::{timer, Context, GameError, GameResult};
+
+// Longest frame time fed to the physics, so a stall doesn't trigger a burst of ticks
+const MAX_FRAME_TIME: f32 = 0.25;
+
+// Seconds the result stays on screen before attract mode starts a new game
+const ATTRACT_DELAY: f32 = 3.0;
+
+// Fastest fast-forward, in physics ticks per frame tick
+const MAX_SIM_SPEED: u32 = 16;
+
+// Straight segments drawn of the predicted ball path
+const TRAJECTORY_SEGMENTS: usize = 8;
+
+// Length of the serve direction arrow in pixels
+const SERVE_ARROW_LENGTH: f32 = 40.0;
+
+// Seconds a screen shake takes to die down, and its starting size in pixels
+const SHAKE_DURATION: f32 = 0.2;
+const SHAKE_MAGNITUDE: f32 = 4.0;
+
+// Size of the ball icons showing the lives left, and the most drawn before
+// switching to a single icon and a count
+const LIFE_ICON_RADIUS: f32 = 6.0;
+const MAX_LIFE_ICONS: usize = 10;
+
+// Where the best score is kept between runs
+const HIGH_SCORE_FILE: &str = "highscore.txt";
 
-struct Block {
-    rect_x1: f32,
-    rect_y1: f32,
-    rect_x2: f32,
-    rect_y2: f32,
-    is_visible: bool, 
+// Printed before giving up on an option the game doesn't know
+const USAGE: &str = "Usage: arkanoid [LAYOUT] [--level LAYOUT] [--seed N] [--controller NAME] \
+[--lives N] [--ball-radius PIXELS] [--time-limit SECONDS] [--exact-collisions] \
+[--bottom-bounce] [--attract] [--top-paddle] [--high-contrast]";
+
+// Thickness of the playfield outline in pixels
+const BORDER_WIDTH: f32 = 2.0;
+
+// Thickness of the block outlines palettes can ask for, in pixels
+const BLOCK_OUTLINE_WIDTH: f32 = 2.0;
+
+// Meshes are built once and positioned with a DrawParam instead of being rebuilt every frame
+struct Meshes {
+    // White, tinted when drawn
+    ball: Mesh,
+    ball_radius: f32,
+    // A white 1x1 square, scaled and tinted for every block and the paddle
+    rect: Mesh,
+    // Outline of the playfield, where the ball bounces; white, tinted when drawn
+    border: Mesh,
 }
 
-impl Block {
-    fn new(x1: f32, y1: f32, width: f32, height: f32) -> Self {
-        Block {
-            rect_x1: x1,
-            rect_y1: y1,
-            rect_x2: x1 + width,
-            rect_y2: y1 + height,
-            is_visible: true,
+impl Meshes {
+    fn get<'a>(
+        cache: &'a mut Option<Meshes>,
+        ctx: &mut Context,
+        radius: f32,
+        field: (f32, f32),
+    ) -> GameResult<&'a Meshes> {
+        match cache {
+            Some(meshes) if meshes.ball_radius != radius => {
+                meshes.ball = Self::ball_mesh(ctx, radius)?;
+                meshes.ball_radius = radius;
+            }
+            Some(_) => {}
+            None => {
+                let rect = Mesh::new_rectangle(
+                    ctx,
+                    DrawMode::fill(),
+                    graphics::Rect::new(0.0, 0.0, 1.0, 1.0),
+                    Color::WHITE,
+                )?;
+                let border = Mesh::new_rectangle(
+                    ctx,
+                    DrawMode::stroke(BORDER_WIDTH),
+                    graphics::Rect::new(0.0, 0.0, field.0, field.1),
+                    Color::WHITE,
+                )?;
+                *cache = Some(Meshes {
+                    ball: Self::ball_mesh(ctx, radius)?,
+                    ball_radius: radius,
+                    rect,
+                    border,
+                });
+            }
         }
+        Ok(cache.as_ref().expect("mesh cache was just filled"))
+    }
+
+    fn ball_mesh(ctx: &mut Context, radius: f32) -> GameResult<Mesh> {
+        Mesh::new_circle(
+            ctx,
+            DrawMode::fill(),
+            Point2 { x: 0.0, y: 0.0 },
+            radius,
+            2.0,
+            Color::WHITE,
+        )
     }
 }
 
-struct Paddle {
-    x: f32,
-    y: f32,
-    width: f32,
-    height: f32,
-    speed: f32,
-    direction: f32,
+// Sound effects, each left out if its file can't be loaded
+struct Sounds {
+    paddle: Option<audio::Source>,
+    block: Option<audio::Source>,
+    life_lost: Option<audio::Source>,
 }
 
-impl Paddle {
-    fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
-        Paddle {
-            x,
-            y,
-            width,
-            height,
-            speed: 5.0,    
-            direction: 1.0, 
+impl Sounds {
+    fn load(ctx: &mut Context) -> Self {
+        Sounds {
+            paddle: audio::Source::new(ctx, "/paddle.wav").ok(),
+            block: audio::Source::new(ctx, "/block.wav").ok(),
+            life_lost: audio::Source::new(ctx, "/life_lost.wav").ok(),
         }
     }
 
-    fn update_position(&mut self) {
-        self.x += self.speed * self.direction;
+    // Bounces play at `pitch`, so faster rallies sound higher
+    fn play(&mut self, ctx: &mut Context, event: GameEvent, pitch: f32) -> GameResult {
+        let (source, pitch) = match event {
+            GameEvent::PaddleHit => (&mut self.paddle, pitch),
+            GameEvent::BlockDestroyed { .. } => (&mut self.block, pitch),
+            GameEvent::LifeLost => (&mut self.life_lost, 1.0),
+            _ => return Ok(()),
+        };
+        match source {
+            Some(source) => {
+                source.set_pitch(pitch);
+                source.play_detached(ctx)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+fn rect_param(x: f32, y: f32, width: f32, height: f32, color: Color) -> DrawParam {
+    DrawParam::new()
+        .dest(Point2 { x, y })
+        .scale(Vector2 {
+            x: width,
+            y: height,
+        })
+        .color(color)
+}
 
-        if self.x <= 0.0 || self.x + self.width >= 800.0 {
-            self.direction = -self.direction; 
+// Faint lines along the edges of the block grid's cells
+fn draw_grid_overlay(
+    ctx: &mut Context,
+    rect: &Mesh,
+    config: &GameConfig,
+    color: Color,
+) -> GameResult {
+    // Lines run across the whole field, lined up with the grid wherever it starts
+    let gap = config.fitted_spacing();
+    let (step_x, step_y) = (config.block_width + gap, config.block_height + gap);
+    let (origin_x, origin_y) = config.grid_origin();
+    let mut x = origin_x - (origin_x / step_x).floor() * step_x;
+    while x <= config.width {
+        for edge in [x, x + config.block_width] {
+            graphics::draw(ctx, rect, rect_param(edge, 0.0, 1.0, config.height, color))?;
         }
+        x += step_x;
     }
+    let mut y = origin_y - (origin_y / step_y).floor() * step_y;
+    while y <= config.height {
+        for edge in [y, y + config.block_height] {
+            graphics::draw(ctx, rect, rect_param(0.0, edge, config.width, 1.0, color))?;
+        }
+        y += step_y;
+    }
+    Ok(())
 }
 
-struct MainState {
-    circle_x: f32,
-    circle_y: f32,
-    radius: f32,
-    velocity_x: f32,
-    velocity_y: f32,
-    blocks: Vec<Block>,
-    paddle: Paddle,
+// Tougher blocks are drawn in a darker shade of their row's color
+fn block_color(block: &Block, palette: Palette) -> Color {
+    // A boss shows its health in its color instead
+    if block.kind == BlockKind::Boss {
+        return palette.block(block);
+    }
+    let shade = 0.3 + 0.7 / block.hits_remaining.max(1) as f32;
+    let color = palette.block(block);
+    Color::new(color.r * shade, color.g * shade, color.b * shade, color.a)
 }
 
-impl MainState {
-    fn new() -> Self {
-        let mut blocks = Vec::new();
-        let block_width = 30.0;
-        let block_height = 30.0;
-        let rows = 5;
-        let cols = 10;
+// The ggez front-end: owns the simulation and everything only needed to draw it
+struct App {
+    game: MainState,
+    meshes: Option<Meshes>,
+    sounds: Sounds,
+    muted: bool,
+    show_debug: bool,
+    show_trajectory: bool,
+    // Physics ticks run for every TIME_STEP of real time
+    sim_speed: u32,
+    // Start a new game by itself a little while after one ends
+    attract_mode: bool,
+    // Seconds since the current game ended
+    time_since_end: f32,
+    // Frame time not yet consumed by fixed physics ticks, in seconds
+    accumulator: f32,
+    left_held: bool,
+    right_held: bool,
+    // The game was paused by the window losing focus, not by the player
+    paused_by_focus: bool,
+    // The controller picked on the title screen
+    controller: PaddleController,
+    // Shake the playfield when a block breaks
+    screen_shake: bool,
+    show_grid: bool,
+    // Seconds left of the current shake
+    shake_time: f32,
+    // Kept apart from the game's generators so shaking can't change a game
+    shake_rng: Rng,
+    // Where the level editor saves its layout
+    layout_path: String,
+}
 
-        for row in 0..rows {
-            for col in 0..cols {
-                let x = col as f32 * (block_width + 5.0); 
-                let y = row as f32 * (block_height + 5.0);
-                blocks.push(Block::new(x, y, block_width, block_height));
-            }
+impl App {
+    fn new(ctx: &mut Context, game: MainState) -> Self {
+        App {
+            game,
+            meshes: None,
+            sounds: Sounds::load(ctx),
+            muted: false,
+            show_debug: false,
+            show_trajectory: false,
+            sim_speed: 1,
+            attract_mode: false,
+            time_since_end: 0.0,
+            accumulator: 0.0,
+            left_held: false,
+            right_held: false,
+            paused_by_focus: false,
+            controller: PaddleController::FollowBall,
+            screen_shake: true,
+            show_grid: false,
+            shake_time: 0.0,
+            shake_rng: Rng::new(0),
+            layout_path: "layout.txt".to_string(),
         }
+    }
 
-        let paddle = Paddle::new(375.0, 550.0, 400.0, 10.0);
+    fn background(&self) -> Color {
+        let config = &self.game.config;
+        config.palette.background(config.background)
+    }
 
-        MainState {
-            circle_x: 400.0,
-            circle_y: 300.0,
-            radius: 15.0,
-            velocity_x: 3.0,
-            velocity_y: 3.0,
-            blocks,
-            paddle,
+    // Black on a light background, white on a dark one
+    fn foreground(&self) -> Color {
+        let background = self.background();
+        let luminance = 0.299 * background.r + 0.587 * background.g + 0.114 * background.b;
+        if luminance > 0.5 {
+            Color::BLACK
+        } else {
+            Color::WHITE
         }
     }
-}
 
-impl EventHandler for MainState {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
-        self.circle_x += self.velocity_x;
-        self.circle_y += self.velocity_y;
+    fn select_controller(&mut self, controller: PaddleController) {
+        self.controller = controller;
+        self.game.controller = controller.build();
+    }
+
+    fn update_manual_direction(&mut self) {
+        let left = if self.left_held { -1.0 } else { 0.0 };
+        let right = if self.right_held { 1.0 } else { 0.0 };
+        self.game.manual_direction = left + right;
+    }
 
-        self.paddle.update_position();
+    fn draw_hud(&self, ctx: &mut Context) -> GameResult {
+        let color = self.foreground();
+        let score = graphics::Text::new(format!(
+            "Score: {}  Best: {}",
+            self.game.current_score(),
+            self.game.best_score()
+        ));
+        graphics::draw(ctx, &score, (Point2 { x: 10.0, y: 10.0 }, color))?;
 
-        if self.circle_x - self.radius <= 0.0 || self.circle_x + self.radius >= 800.0 {
-            self.velocity_x = -self.velocity_x;
-        }
-        if self.circle_y - self.radius <= 0.0 || self.circle_y + self.radius >= 600.0 {
-            self.velocity_y = -self.velocity_y;
+        if self.game.current_combo() > 1 {
+            let combo = graphics::Text::new(format!("Combo: x{}", self.game.current_combo()));
+            graphics::draw(ctx, &combo, (Point2 { x: 10.0, y: 30.0 }, color))?;
         }
 
-        if self.circle_y + self.radius >= self.paddle.y
-            && self.circle_x >= self.paddle.x
-            && self.circle_x <= self.paddle.x + self.paddle.width
-        {
-            self.velocity_y = -self.velocity_y;
+        let level = graphics::Text::new(format!("Level: {}", self.game.current_level()));
+        let level_x = (self.game.config.width - level.width(ctx)) / 2.0;
+        graphics::draw(
+            ctx,
+            &level,
+            (
+                Point2 {
+                    x: level_x,
+                    y: 10.0,
+                },
+                color,
+            ),
+        )?;
 
-            let paddle_center = self.paddle.x + (self.paddle.width / 2.0);
-            let distance_from_center = self.circle_x - paddle_center;
+        let controller = graphics::Text::new(format!("Controller: {}", self.controller.name()));
+        let controller_x = (self.game.config.width - controller.width(ctx)) / 2.0;
+        graphics::draw(
+            ctx,
+            &controller,
+            (
+                Point2 {
+                    x: controller_x,
+                    y: 30.0,
+                },
+                color,
+            ),
+        )?;
+
+        self.draw_life_icons(ctx)?;
 
-            // If circle is far from center we increase its speed
-            self.velocity_x += distance_from_center * 0.05;
+        if let Some(time_left) = self.game.time_left() {
+            let time = graphics::Text::new(format!("Time: {}", time_left.ceil()));
+            let time_x = self.game.config.width - time.width(ctx) - 10.0;
+            graphics::draw(ctx, &time, (Point2 { x: time_x, y: 30.0 }, color))?;
         }
 
-        for block in self.blocks.iter_mut() {
-            if block.is_visible {
-                if let Some((collision_x, collision_y)) = check_circle_rectangle_collision(
-                    Decimal::from_f32(self.circle_x).unwrap(),
-                    Decimal::from_f32(self.circle_y).unwrap(),
-                    Decimal::from_f32(self.radius).unwrap(),
-                    Decimal::from_f32(block.rect_x1).unwrap(),
-                    Decimal::from_f32(block.rect_y1).unwrap(),
-                    Decimal::from_f32(block.rect_x2).unwrap(),
-                    Decimal::from_f32(block.rect_y2).unwrap(),
-                ) {
-                    if collision_x {
-                        self.velocity_x = -self.velocity_x;
-                    }
-                    if collision_y {
-                        self.velocity_y = -self.velocity_y;
-                    }
-
-                    block.is_visible = false;
+        Ok(())
+    }
+
+    fn draw_editor_help(&self, ctx: &mut Context) -> GameResult {
+        let text = graphics::Text::new(format!(
+            "Click to place or remove a block, Ctrl+S saves to {}, E plays",
+            self.layout_path
+        ));
+        let x = (self.game.config.width - text.width(ctx)) / 2.0;
+        let y = self.game.config.height - text.height(ctx) - 10.0;
+        graphics::draw(ctx, &text, (Point2 { x, y }, self.foreground()))
+    }
+
+    // Runs one tick while paused and prints what the controller did and
+    // where every ball ended up
+    fn single_step(&mut self) {
+        match self.game.single_step() {
+            Ok(Some(action)) => {
+                println!("tick {}: {:?}", self.game.current_tick(), action);
+                for ball in &self.game.balls {
+                    println!(
+                        "  ball at ({:.2}, {:.2}) moving ({:.2}, {:.2})",
+                        ball.x, ball.y, ball.velocity_x, ball.velocity_y
+                    );
                 }
             }
+            Ok(None) => {}
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    fn save_layout(&self) {
+        match self.game.save_layout(&self.layout_path) {
+            Ok(()) => println!("Saved layout to {}", self.layout_path),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // A small ball per life left in the top-right corner, or past
+    // MAX_LIFE_ICONS a single one with the count beside it
+    fn draw_life_icons(&self, ctx: &mut Context) -> GameResult {
+        let Some(meshes) = &self.meshes else {
+            return Ok(());
+        };
+        let color = self.game.config.palette.ball();
+        let scale = LIFE_ICON_RADIUS / meshes.ball_radius;
+        let spacing = LIFE_ICON_RADIUS * 2.0 + 4.0;
+        let lives = self.game.lives as usize;
+        let icons = if lives > MAX_LIFE_ICONS { 1 } else { lives };
+        let mut x = self.game.config.width - 10.0 - LIFE_ICON_RADIUS;
+        if lives > MAX_LIFE_ICONS {
+            let count = graphics::Text::new(format!("x{}", lives));
+            let dest = Point2 {
+                x: self.game.config.width - 10.0 - count.width(ctx),
+                y: 10.0 + LIFE_ICON_RADIUS - count.height(ctx) / 2.0,
+            };
+            graphics::draw(ctx, &count, (dest, self.foreground()))?;
+            x -= count.width(ctx) + 4.0;
+        }
+        for _ in 0..icons {
+            let param = DrawParam::new()
+                .dest(Point2 {
+                    x,
+                    y: 10.0 + LIFE_ICON_RADIUS,
+                })
+                .scale(Vector2 { x: scale, y: scale })
+                .color(color);
+            graphics::draw(ctx, &meshes.ball, param)?;
+            x -= spacing;
+        }
+        Ok(())
+    }
+
+    // Frame rate and simulation counts in the bottom-left corner
+    fn draw_debug(&self, ctx: &mut Context) -> GameResult {
+        let speed = self
+            .game
+            .balls
+            .iter()
+            .map(|ball| ball.speed())
+            .fold(0.0, f32::max);
+        let text = graphics::Text::new(format!(
+            "FPS: {:.0}\nSim speed: x{}\nBlocks: {}\nBalls: {}\nSpeed: {:.0}",
+            timer::fps(ctx),
+            self.sim_speed,
+            self.game.blocks_remaining(),
+            self.game.balls.len(),
+            speed
+        ));
+        let y = self.game.config.height - text.height(ctx) - 10.0;
+        graphics::draw(ctx, &text, (Point2 { x: 10.0, y }, self.foreground()))
+    }
+
+    // The path the predictive controller expects each ball to take to the paddle
+    fn draw_trajectories(&self, ctx: &mut Context) -> GameResult {
+        for ball in &self.game.balls {
+            let points: Vec<Point2<f32>> = self
+                .game
+                .view(TIME_STEP)
+                .predicted_path(ball, TRAJECTORY_SEGMENTS)
+                .into_iter()
+                .map(|(x, y)| Point2 { x, y })
+                .collect();
+            // A line needs two distinct points; a resting ball has no path
+            if points.len() < 2 || points[0] == points[1] {
+                continue;
+            }
+            let color = self.game.config.palette.trajectory();
+            let line = Mesh::new_line(ctx, &points, 1.0, color)?;
+            graphics::draw(ctx, &line, DrawParam::new())?;
+        }
+        Ok(())
+    }
+
+    // An arrow from the resting ball pointing the way it will be served
+    fn draw_serve_arrow(&self, ctx: &mut Context) -> GameResult {
+        let ball = match self.game.balls.first() {
+            Some(ball) => ball,
+            None => return Ok(()),
+        };
+        let angle = self.game.serve_angle.to_radians();
+        let (dir_x, dir_y) = (angle.sin(), -angle.cos());
+        let start = self.game.config.ball_radius + 5.0;
+        let end = start + SERVE_ARROW_LENGTH;
+        let tip = Point2 {
+            x: ball.x + dir_x * end,
+            y: ball.y + dir_y * end,
+        };
+        // Arrowhead sides, swept back from the tip on either side of the shaft
+        let head = |side: f32| Point2 {
+            x: tip.x - dir_x * 8.0 + side * dir_y * 5.0,
+            y: tip.y - dir_y * 8.0 - side * dir_x * 5.0,
+        };
+
+        let arrow = graphics::MeshBuilder::new()
+            .line(
+                &[
+                    Point2 {
+                        x: ball.x + dir_x * start,
+                        y: ball.y + dir_y * start,
+                    },
+                    tip,
+                ],
+                2.0,
+                self.foreground(),
+            )?
+            .line(&[head(1.0), tip, head(-1.0)], 2.0, self.foreground())?
+            .build(ctx)?;
+        graphics::draw(ctx, &arrow, DrawParam::new())
+    }
+
+    // Whole seconds left before the ball can be served, above the paddle
+    fn draw_respawn_countdown(&self, ctx: &mut Context) -> GameResult {
+        let text = graphics::Text::new(format!("{}", self.game.respawn_timer.ceil()));
+        let paddle = &self.game.paddle;
+        let x = paddle.x + (paddle.width - text.width(ctx)) / 2.0;
+        let y = paddle.y
+            - self.game.config.ball_radius * 2.0
+            - SERVE_ARROW_LENGTH
+            - text.height(ctx)
+            - 10.0;
+        graphics::draw(ctx, &text, (Point2 { x, y }, self.foreground()))
+    }
+
+    fn draw_menu(&self, ctx: &mut Context) -> GameResult {
+        let lines = [
+            "ARKANOID".to_string(),
+            "Press Enter to start".to_string(),
+            format!("Controller: < {} >", self.controller.name()),
+        ];
+        let mut y = self.game.config.height / 3.0;
+        for line in lines {
+            let text = graphics::Text::new(line);
+            let x = (self.game.config.width - text.width(ctx)) / 2.0;
+            graphics::draw(ctx, &text, (Point2 { x, y }, self.foreground()))?;
+            y += 2.0 * text.height(ctx);
+        }
+        Ok(())
+    }
+
+    fn draw_banner(&self, ctx: &mut Context, message: &str) -> GameResult {
+        let text = graphics::Text::new(message);
+        let x = (self.game.config.width - text.width(ctx)) / 2.0;
+        let y = (self.game.config.height - text.height(ctx)) / 2.0;
+        graphics::draw(ctx, &text, (Point2 { x, y }, self.foreground()))
+    }
+}
+
+impl EventHandler for App {
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        // Run the physics at a fixed rate no matter how fast frames come in,
+        // dropping time after a long stall rather than trying to catch up
+        let frame_time = timer::delta(ctx).as_secs_f32().min(MAX_FRAME_TIME);
+        self.accumulator += frame_time;
+        let was_running = self.game.state.is_running();
+        while self.accumulator >= TIME_STEP {
+            for _ in 0..self.sim_speed {
+                self.game.step()?;
+            }
+            self.accumulator -= TIME_STEP;
+        }
+        if was_running && !self.game.state.is_running() {
+            if let Err(e) = self.game.save_high_score(HIGH_SCORE_FILE) {
+                eprintln!("{}", e);
+            }
+        }
+        self.shake_time = (self.shake_time - frame_time).max(0.0);
+        // Events don't say which ball bounced, so go by the fastest
+        let fastest = self
+            .game
+            .balls
+            .iter()
+            .map(|b| b.speed())
+            .fold(0.0, f32::max);
+        let pitch = self.game.config.bounce_pitch(fastest);
+        for (_, event) in self.game.drain_events() {
+            if self.screen_shake && matches!(event, GameEvent::BlockDestroyed { .. }) {
+                self.shake_time = SHAKE_DURATION;
+            }
+            if !self.muted {
+                self.sounds.play(ctx, event, pitch)?;
+            }
+        }
+
+        if self.game.state.is_running() {
+            self.time_since_end = 0.0;
+        } else if self.attract_mode
+            && !matches!(self.game.state, GameState::Menu | GameState::Editing)
+        {
+            self.time_since_end += frame_time;
+            if self.time_since_end >= ATTRACT_DELAY {
+                // Vary the serves too so the demo doesn't repeat itself
+                let seed = self.game.rng.next_u64();
+                self.game.random_launch = true;
+                self.game.reset_with_seed(seed);
+            }
         }
 
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        graphics::clear(ctx, Color::WHITE);
+        let foreground = self.foreground();
+        graphics::clear(ctx, self.background());
+        let palette = self.game.config.palette;
 
-        let circle = Mesh::new_circle(
-            ctx,
-            DrawMode::fill(),
-            Point2 {
-                x: self.circle_x,
-                y: self.circle_y,
-            },
-            self.radius,
-            2.0,
-            Color::BLUE,
-        )?;
-        graphics::draw(ctx, &circle, (Point2 { x: 0.0, y: 0.0 },))?;
+        match self.game.state {
+            GameState::Menu => {
+                self.draw_menu(ctx)?;
+                return graphics::present(ctx);
+            }
+            GameState::GameOver => {
+                self.draw_banner(ctx, "Game Over")?;
+                return graphics::present(ctx);
+            }
+            GameState::Won => {
+                self.draw_banner(ctx, "You Win!")?;
+                return graphics::present(ctx);
+            }
+            GameState::Editing | GameState::Serving | GameState::Playing => {}
+        }
+
+        // Shake by moving the view rather than everything drawn in it, and
+        // put the view back for the HUD
+        let view = graphics::screen_coordinates(ctx);
+        if self.screen_shake && self.shake_time > 0.0 {
+            let size = SHAKE_MAGNITUDE * self.shake_time / SHAKE_DURATION;
+            let mut shaken = view;
+            shaken.x += self.shake_rng.range(-size, size);
+            shaken.y += self.shake_rng.range(-size, size);
+            graphics::set_screen_coordinates(ctx, shaken)?;
+        }
+
+        let field = (self.game.config.width, self.game.config.height);
+        let meshes = Meshes::get(&mut self.meshes, ctx, self.game.config.ball_radius, field)?;
+        graphics::draw(ctx, &meshes.border, DrawParam::new().color(foreground))?;
+        if self.show_grid || self.game.state == GameState::Editing {
+            let color = Color {
+                a: 0.15,
+                ..foreground
+            };
+            draw_grid_overlay(ctx, &meshes.rect, &self.game.config, color)?;
+        }
+
+        // A ghosting ball is drawn see-through, and a slowed or sped up one
+        // in the color of the power-up that did it
+        let ball_color = if self.game.ghost_ticks > 0 {
+            palette.ghost_ball()
+        } else if self.game.speed_factor < 1.0 {
+            palette.power_up(PowerUpKind::SlowBall)
+        } else if self.game.speed_factor > 1.0 {
+            palette.power_up(PowerUpKind::FastBall)
+        } else {
+            palette.ball()
+        };
+        // Older trail circles are smaller and fainter
+        let trail_color = palette.trail(ball_color);
+        let trail_length = self.game.trail.len();
+        for (age, centers) in self.game.trail.iter().rev().enumerate() {
+            let fade = 1.0 - (age + 1) as f32 / (trail_length + 1) as f32;
+            let color = Color {
+                a: trail_color.a * fade,
+                ..trail_color
+            };
+            for &(x, y) in centers {
+                let param = DrawParam::new()
+                    .dest(Point2 { x, y })
+                    .scale(Vector2 { x: fade, y: fade })
+                    .color(color);
+                graphics::draw(ctx, &meshes.ball, param)?;
+            }
+        }
+        for ball in &self.game.balls {
+            graphics::draw(
+                ctx,
+                &meshes.ball,
+                (
+                    Point2 {
+                        x: ball.x,
+                        y: ball.y,
+                    },
+                    ball_color,
+                ),
+            )?;
+        }
 
-        for block in &self.blocks {
+        // An outline is the block drawn in the foreground color with the
+        // block itself drawn inset over it
+        let inset = if palette.outlines_blocks() {
+            BLOCK_OUTLINE_WIDTH
+        } else {
+            0.0
+        };
+        for block in &self.game.blocks {
             if block.is_visible {
-                let rect = graphics::Rect::new(
-                    block.rect_x1,
-                    block.rect_y1,
-                    block.rect_x2 - block.rect_x1,
-                    block.rect_y2 - block.rect_y1,
+                let (x, y) = (block.rect_x1, block.rect_y1);
+                let (width, height) = (block.rect_x2 - x, block.rect_y2 - y);
+                if inset > 0.0 {
+                    let param = rect_param(x, y, width, height, foreground);
+                    graphics::draw(ctx, &meshes.rect, param)?;
+                }
+                let param = rect_param(
+                    x + inset,
+                    y + inset,
+                    width - 2.0 * inset,
+                    height - 2.0 * inset,
+                    block_color(block, palette),
                 );
-                let rectangle = Mesh::new_rectangle(ctx, DrawMode::fill(), rect, Color::GREEN)?;
-                graphics::draw(ctx, &rectangle, (Point2 { x: 0.0, y: 0.0 },))?;
+                graphics::draw(ctx, &meshes.rect, param)?;
             }
         }
 
-        let paddle_rect = graphics::Rect::new(
-            self.paddle.x,
-            self.paddle.y,
-            self.paddle.width,
-            self.paddle.height,
-        );
-        let paddle = Mesh::new_rectangle(ctx, DrawMode::fill(), paddle_rect, Color::RED)?;
-        graphics::draw(ctx, &paddle, (Point2 { x: 0.0, y: 0.0 },))?;
+        for power_up in &self.game.power_ups {
+            let param = rect_param(
+                power_up.x,
+                power_up.y,
+                POWER_UP_SIZE,
+                POWER_UP_SIZE,
+                palette.power_up(power_up.kind),
+            );
+            graphics::draw(ctx, &meshes.rect, param)?;
+        }
+
+        for particle in &self.game.particles {
+            let color = Color {
+                a: particle.alpha(),
+                ..particle.color
+            };
+            let param = rect_param(
+                particle.x - PARTICLE_SIZE / 2.0,
+                particle.y - PARTICLE_SIZE / 2.0,
+                PARTICLE_SIZE,
+                PARTICLE_SIZE,
+                color,
+            );
+            graphics::draw(ctx, &meshes.rect, param)?;
+        }
+
+        if let Some(shield) = &self.game.shield {
+            let param = rect_param(
+                shield.rect_x1,
+                shield.rect_y1,
+                shield.rect_x2 - shield.rect_x1,
+                shield.rect_y2 - shield.rect_y1,
+                palette.shield(),
+            );
+            graphics::draw(ctx, &meshes.rect, param)?;
+        }
+
+        for laser in &self.game.lasers {
+            let param = rect_param(laser.x, laser.y, LASER_WIDTH, LASER_HEIGHT, palette.laser());
+            graphics::draw(ctx, &meshes.rect, param)?;
+        }
+
+        for popup in &self.game.popups {
+            let text = graphics::Text::new(popup.text.as_str());
+            let dest = Point2 {
+                x: popup.x - text.width(ctx) / 2.0,
+                y: popup.y - text.height(ctx) / 2.0,
+            };
+            let color = Color {
+                a: popup.alpha(),
+                ..palette.popup(foreground)
+            };
+            graphics::draw(ctx, &text, (dest, color))?;
+        }
+
+        for paddle in std::iter::once(&self.game.paddle).chain(&self.game.top_paddle) {
+            let param = rect_param(
+                paddle.x,
+                paddle.y,
+                paddle.width,
+                paddle.height,
+                palette.paddle(),
+            );
+            graphics::draw(ctx, &meshes.rect, param)?;
+        }
+
+        if self.game.state == GameState::Serving {
+            self.draw_serve_arrow(ctx)?;
+            if self.game.respawn_timer > 0.0 {
+                self.draw_respawn_countdown(ctx)?;
+            }
+        }
+        if self.show_trajectory {
+            self.draw_trajectories(ctx)?;
+        }
+        graphics::set_screen_coordinates(ctx, view)?;
+
+        self.draw_hud(ctx)?;
+        if self.game.state == GameState::Editing {
+            self.draw_editor_help(ctx)?;
+        }
+        if self.show_debug {
+            self.draw_debug(ctx)?;
+        }
+
+        if self.game.paused {
+            self.draw_banner(ctx, "PAUSED")?;
+        }
 
         graphics::present(ctx)
     }
+
+    // Keep the playfield's own coordinates whatever the window size, scaled
+    // to fit and centered with the spare room left blank around it
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        let scale = (width / self.game.config.width).min(height / self.game.config.height);
+        let (view_width, view_height) = (width / scale, height / scale);
+        let view = graphics::Rect::new(
+            (self.game.config.width - view_width) / 2.0,
+            (self.game.config.height - view_height) / 2.0,
+            view_width,
+            view_height,
+        );
+        if let Err(e) = graphics::set_screen_coordinates(ctx, view) {
+            eprintln!("Failed to rescale the view: {}", e);
+        }
+    }
+
+    // Pause while the window is in the background, resuming on return only
+    // if it was this that paused it
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) {
+        if !gained {
+            if !self.game.paused {
+                self.game.paused = true;
+                self.paused_by_focus = true;
+            }
+            // Key releases are not seen while unfocused
+            self.left_held = false;
+            self.right_held = false;
+            self.update_manual_direction();
+        } else if self.paused_by_focus {
+            self.game.paused = false;
+            self.paused_by_focus = false;
+        }
+    }
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        repeat: bool,
+    ) {
+        let in_menu = self.game.state == GameState::Menu;
+        let editing = self.game.state == GameState::Editing;
+        match keycode {
+            KeyCode::E if !repeat && editing => self.game.stop_editing(),
+            KeyCode::E if !repeat => self.game.start_editing(),
+            KeyCode::S if !repeat && editing && keymods.contains(KeyMods::CTRL) => {
+                self.save_layout()
+            }
+            KeyCode::Return | KeyCode::NumpadEnter if in_menu => self.game.start(),
+            KeyCode::Left if in_menu && !repeat => {
+                self.select_controller(self.controller.cycle(-1))
+            }
+            KeyCode::Right if in_menu && !repeat => {
+                self.select_controller(self.controller.cycle(1))
+            }
+            // Serves or releases the ball, or toggles the pause when there is
+            // nothing to launch, such as during the respawn delay
+            KeyCode::Space if !repeat && !self.game.paused && self.game.can_serve() => {
+                self.game.serve()
+            }
+            KeyCode::Space if !repeat && !self.game.paused && self.game.has_stuck_balls() => {
+                self.game.release()
+            }
+            KeyCode::Space if !repeat => self.game.paused = !self.game.paused,
+            KeyCode::C if !repeat => self.select_controller(self.controller.cycle(1)),
+            KeyCode::M if !repeat => self.muted = !self.muted,
+            KeyCode::F if !repeat => self.show_debug = !self.show_debug,
+            KeyCode::T if !repeat => self.show_trajectory = !self.show_trajectory,
+            KeyCode::S if !repeat => self.screen_shake = !self.screen_shake,
+            KeyCode::G if !repeat => self.show_grid = !self.show_grid,
+            KeyCode::A if !repeat => self.attract_mode = !self.attract_mode,
+            KeyCode::R if !repeat => self.game.reset(),
+            KeyCode::N if self.game.paused => self.single_step(),
+            KeyCode::Plus | KeyCode::Equals | KeyCode::NumpadAdd => {
+                self.sim_speed = (self.sim_speed + 1).min(MAX_SIM_SPEED)
+            }
+            KeyCode::Minus | KeyCode::NumpadSubtract => {
+                self.sim_speed = self.sim_speed.saturating_sub(1).max(1)
+            }
+            KeyCode::Up => self.game.fire(),
+            KeyCode::Left => self.left_held = true,
+            KeyCode::Right => self.right_held = true,
+            _ => {}
+        }
+        self.update_manual_direction();
+    }
+
+    // In the editor a click toggles the block under it; the window position
+    // is mapped through the view, which may be scaled or shaken
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if button != MouseButton::Left || self.game.state != GameState::Editing {
+            return;
+        }
+        let view = graphics::screen_coordinates(ctx);
+        let (width, height) = graphics::drawable_size(ctx);
+        self.game
+            .toggle_block_at(view.x + x * view.w / width, view.y + y * view.h / height);
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods) {
+        match keycode {
+            KeyCode::Left => self.left_held = false,
+            KeyCode::Right => self.right_held = false,
+            _ => {}
+        }
+        self.update_manual_direction();
+    }
 }
 
 fn main() -> GameResult {
-    let (ctx, event_loop) = ggez::ContextBuilder::new("circle_rectangle_collision", "Author")
+    let mut layout = None;
+    let mut controller = PaddleController::FollowBall;
+    let mut exact_collisions = false;
+    let mut bottom_wall = BottomBehavior::Lose;
+    let mut attract_mode = false;
+    let mut top_paddle = false;
+    let mut seed = None;
+    let mut config = GameConfig::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--exact-collisions" => exact_collisions = true,
+            "--bottom-bounce" => bottom_wall = BottomBehavior::Bounce,
+            "--attract" => attract_mode = true,
+            "--top-paddle" => top_paddle = true,
+            "--high-contrast" => config.palette = Palette::HighContrast,
+            "--controller" => {
+                let name = args.next().unwrap_or_default();
+                controller = name.parse().map_err(GameError::ConfigError)?;
+            }
+            "--seed" => {
+                let value = args.next().unwrap_or_default();
+                let parsed = value
+                    .parse()
+                    .map_err(|_| GameError::ConfigError(format!("Invalid seed: {}", value)))?;
+                seed = Some(parsed);
+            }
+            "--level" => layout = args.next(),
+            "--lives" => {
+                let value = args.next().unwrap_or_default();
+                config.lives = value
+                    .parse()
+                    .map_err(|_| GameError::ConfigError(format!("Invalid lives: {}", value)))?;
+            }
+            "--ball-radius" => {
+                let value = args.next().unwrap_or_default();
+                config.ball_radius = value.parse().map_err(|_| {
+                    GameError::ConfigError(format!("Invalid ball radius: {}", value))
+                })?;
+            }
+            "--time-limit" => {
+                let value = args.next().unwrap_or_default();
+                let seconds: f32 = value.parse().map_err(|_| {
+                    GameError::ConfigError(format!("Invalid time limit: {}", value))
+                })?;
+                let limit = std::time::Duration::try_from_secs_f32(seconds).map_err(|_| {
+                    GameError::ConfigError(format!("Invalid time limit: {}", value))
+                })?;
+                config.time_limit = Some(limit);
+            }
+            _ if !arg.starts_with('-') => layout = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                return Err(GameError::ConfigError(format!("Unknown option: {}", arg)));
+            }
+        }
+    }
+
+    // Look for sounds next to Cargo.toml when run through cargo
+    let resources = match std::env::var("CARGO_MANIFEST_DIR") {
+        Ok(dir) => std::path::Path::new(&dir).join("resources"),
+        Err(_) => std::path::PathBuf::from("resources"),
+    };
+
+    config.validate()?;
+    let (mut ctx, event_loop) = ggez::ContextBuilder::new("circle_rectangle_collision", "Author")
+        .window_setup(WindowSetup::default().title("Arkanoid"))
+        .window_mode(
+            WindowMode::default()
+                .dimensions(config.width, config.height)
+                .resizable(true),
+        )
+        .add_resource_path(resources)
         .build()
         .expect("Failed to build ggez context");
 
-    let state = MainState::new();
-    event::run(ctx, event_loop, state)
+    let mut state = match &layout {
+        Some(path) => MainState::from_layout(path, config)?,
+        None => MainState::new(config),
+    };
+    // Seeded from the OS unless one was given; printed so a run can be replayed
+    let seed = seed.unwrap_or_else(|| RandomState::new().build_hasher().finish());
+    println!("Seed: {}", seed);
+    state.random_launch = true;
+    state.reset_with_seed(seed);
+    state.load_high_score(HIGH_SCORE_FILE);
+    state.exact_collisions = exact_collisions;
+    state.bottom_wall = bottom_wall;
+    if top_paddle {
+        state.enable_top_paddle();
+    }
+    // The demo plays by itself, so only a player gets the title screen
+    if !attract_mode {
+        state.state = GameState::Menu;
+    }
+    let mut app = App::new(&mut ctx, state);
+    if let Some(path) = layout {
+        app.layout_path = path;
+    }
+    app.select_controller(controller);
+    app.attract_mode = attract_mode;
+    event::run(ctx, event_loop, app)
 }
@@ -0,0 +1,305 @@
+use serde::{Deserialize, Serialize};
+
+/// Largest angle from vertical, in degrees, the ball leaves the paddle at
+pub const MAX_BOUNCE_ANGLE: f32 = 60.0;
+/// Closest to vertical, in degrees, a ball leaves the paddle at unless it
+/// came straight down onto the dead center
+pub const MIN_BOUNCE_ANGLE: f32 = 10.0;
+
+/// Extrapolates where a point moving from (circle_x, circle_y) crosses the
+/// line y = paddle_y, reflecting off the walls at x = 0, x = field_width and y = 0.
+pub fn predict_landing_x(
+    circle_x: f32,
+    circle_y: f32,
+    velocity_x: f32,
+    velocity_y: f32,
+    paddle_y: f32,
+    field_width: f32,
+) -> f32 {
+    if velocity_y == 0.0 || field_width <= 0.0 {
+        return circle_x;
+    }
+
+    // A rising ball goes up to the ceiling first and comes back down
+    let distance_y = if velocity_y > 0.0 {
+        paddle_y - circle_y
+    } else {
+        circle_y + paddle_y
+    };
+    let ticks = distance_y / velocity_y.abs();
+    let x = circle_x + velocity_x * ticks;
+
+    // Unfold the side-wall bounces: the path repeats every two field widths
+    let folded = x.rem_euclid(2.0 * field_width);
+    if folded > field_width {
+        2.0 * field_width - folded
+    } else {
+        folded
+    }
+}
+
+/// The points where the path `predict_landing_x` extrapolates turns: the
+/// start, each wall and ceiling bounce, and the landing point on the paddle
+/// line. Stops early after `max_segments` straight segments.
+pub fn predict_path(
+    circle_x: f32,
+    circle_y: f32,
+    velocity_x: f32,
+    velocity_y: f32,
+    paddle_y: f32,
+    field_width: f32,
+    max_segments: usize,
+) -> Vec<(f32, f32)> {
+    let mut points = vec![(circle_x, circle_y)];
+    if velocity_y == 0.0 || field_width <= 0.0 {
+        return points;
+    }
+
+    let (mut x, mut y) = (circle_x, circle_y);
+    let (mut vx, mut vy) = (velocity_x, velocity_y);
+    while points.len() <= max_segments {
+        let to_floor = if vy > 0.0 {
+            (paddle_y - y) / vy
+        } else {
+            f32::INFINITY
+        };
+        let to_ceiling = if vy < 0.0 { -y / vy } else { f32::INFINITY };
+        let to_wall = if vx > 0.0 {
+            (field_width - x) / vx
+        } else if vx < 0.0 {
+            -x / vx
+        } else {
+            f32::INFINITY
+        };
+
+        if to_floor <= to_wall && to_floor <= to_ceiling {
+            // End exactly where the controller aims
+            let landing_x = predict_landing_x(
+                circle_x,
+                circle_y,
+                velocity_x,
+                velocity_y,
+                paddle_y,
+                field_width,
+            );
+            points.push((landing_x, paddle_y));
+            break;
+        }
+        let t = to_wall.min(to_ceiling).max(0.0);
+        x += vx * t;
+        y += vy * t;
+        points.push((x, y));
+        if to_wall <= to_ceiling {
+            vx = -vx;
+        }
+        if to_ceiling <= to_wall {
+            vy = -vy;
+        }
+    }
+    points
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Paddle {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Pixels per second
+    pub speed: f32,
+}
+
+impl Paddle {
+    pub fn new(x: f32, y: f32, width: f32, height: f32, speed: f32) -> Self {
+        Paddle {
+            x,
+            y,
+            width,
+            height,
+            speed,
+        }
+    }
+
+    /// The paddle's corners as (x1, y1, x2, y2).
+    pub fn rect(&self) -> (f32, f32, f32, f32) {
+        (self.x, self.y, self.x + self.width, self.y + self.height)
+    }
+
+    /// Radius of the rounded ends, half circles as tall as the paddle.
+    pub fn end_radius(&self) -> f32 {
+        self.height / 2.0
+    }
+
+    /// The center of the rounded end nearest `x`, or `None` while `x` is
+    /// over the flat middle of the paddle.
+    pub fn end_center(&self, x: f32) -> Option<(f32, f32)> {
+        let radius = self.end_radius();
+        let y = self.y + radius;
+        if x < self.x + radius {
+            Some((self.x + radius, y))
+        } else if x > self.x + self.width - radius {
+            Some((self.x + self.width - radius, y))
+        } else {
+            None
+        }
+    }
+
+    /// Angle from vertical, in degrees and positive to the right, that a
+    /// ball landing at `x` leaves at: turned to the side of the center it
+    /// landed on, further the further out it is, up to `MAX_BOUNCE_ANGLE` at
+    /// the ends. It never leaves closer to vertical than `MIN_BOUNCE_ANGLE`,
+    /// so the ball can't settle into going straight up and down one column;
+    /// a ball landing dead center keeps heading the way its `velocity_x`
+    /// took it.
+    pub fn bounce_angle(&self, x: f32, velocity_x: f32) -> f32 {
+        let center = self.x + self.width / 2.0;
+        let offset = ((x - center) / (self.width / 2.0)).clamp(-1.0, 1.0);
+        let side = if offset != 0.0 { offset } else { velocity_x };
+        let angle = offset.abs() * MAX_BOUNCE_ANGLE;
+        if side == 0.0 {
+            0.0
+        } else {
+            angle.max(MIN_BOUNCE_ANGLE).copysign(side)
+        }
+    }
+
+    /// Moves the paddle in `direction` (-1 left, 0 stop, 1 right), staying inside the field.
+    pub fn move_by(&mut self, direction: f32, field_width: f32, dt: f32) {
+        let x = self.x + self.speed * direction * dt;
+        self.x = x.clamp(0.0, (field_width - self.width).max(0.0));
+    }
+
+    /// Which way to move the center toward `target_x`, staying put once it
+    /// is within half a tick's travel so the paddle doesn't jitter around it.
+    pub fn action_toward(&self, target_x: f32, dt: f32) -> PaddleAction {
+        let offset = target_x - (self.x + self.width / 2.0);
+        if offset.abs() <= self.speed * dt / 2.0 {
+            PaddleAction::Stay
+        } else if offset < 0.0 {
+            PaddleAction::MoveLeft
+        } else {
+            PaddleAction::MoveRight
+        }
+    }
+}
+
+/// What the paddle does for one tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaddleAction {
+    MoveLeft,
+    Stay,
+    MoveRight,
+}
+
+impl PaddleAction {
+    pub fn direction(self) -> f32 {
+        match self {
+            PaddleAction::MoveLeft => -1.0,
+            PaddleAction::Stay => 0.0,
+            PaddleAction::MoveRight => 1.0,
+        }
+    }
+}
+
+/// The built-in controllers, by name; `build` makes one to plug into `MainState`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaddleController {
+    /// Sweep left and right, ignoring the ball
+    Bounce,
+    /// Chase the ball while it is descending
+    FollowBall,
+    /// Move ahead of time to where the ball will cross the paddle line
+    Predictive,
+    /// Driven by the player through `MainState::manual_direction`
+    Manual,
+}
+
+impl PaddleController {
+    /// Every controller, in the order menus cycle through them
+    pub const ALL: [PaddleController; 4] = [
+        PaddleController::Bounce,
+        PaddleController::FollowBall,
+        PaddleController::Predictive,
+        PaddleController::Manual,
+    ];
+
+    /// The name `from_str` accepts for this controller.
+    pub fn name(self) -> &'static str {
+        match self {
+            PaddleController::Bounce => "bounce",
+            PaddleController::FollowBall => "follow",
+            PaddleController::Predictive => "predictive",
+            PaddleController::Manual => "manual",
+        }
+    }
+
+    /// The controller `steps` places further along `ALL`, wrapping around
+    /// either end.
+    pub fn cycle(self, steps: isize) -> Self {
+        let len = Self::ALL.len() as isize;
+        let index = Self::ALL.iter().position(|&c| c == self).unwrap_or(0) as isize;
+        Self::ALL[(index + steps).rem_euclid(len) as usize]
+    }
+}
+
+impl std::str::FromStr for PaddleController {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        PaddleController::ALL
+            .into_iter()
+            .find(|controller| controller.name() == name)
+            .ok_or_else(|| format!("Unknown paddle controller: {}", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn predicts_a_straight_drop() {
+        assert_eq!(
+            predict_landing_x(100.0, 0.0, 10.0, 10.0, 100.0, 800.0),
+            200.0
+        );
+        assert_eq!(
+            predict_landing_x(300.0, 50.0, 0.0, 20.0, 100.0, 800.0),
+            300.0
+        );
+    }
+
+    #[test]
+    fn predicts_a_landing_after_a_wall_bounce() {
+        // Would cross at 900, so bounces back off the right wall to 700
+        assert_eq!(
+            predict_landing_x(600.0, 0.0, 30.0, 10.0, 100.0, 800.0),
+            700.0
+        );
+        // Would cross at -50, so bounces off the left wall to 50
+        assert_eq!(
+            predict_landing_x(50.0, 0.0, -10.0, 10.0, 100.0, 800.0),
+            50.0
+        );
+    }
+
+    #[test]
+    fn predicts_a_rising_ball_via_the_ceiling() {
+        // 50 up to the ceiling and 100 back down
+        assert_eq!(
+            predict_landing_x(100.0, 50.0, 10.0, -10.0, 100.0, 800.0),
+            250.0
+        );
+    }
+
+    #[test]
+    fn near_center_bounce_keeps_a_minimum_angle() {
+        let paddle = Paddle::new(360.0, 550.0, 80.0, 10.0, 300.0);
+        assert_eq!(paddle.bounce_angle(401.0, 0.0), MIN_BOUNCE_ANGLE);
+        assert_eq!(paddle.bounce_angle(399.0, 50.0), -MIN_BOUNCE_ANGLE);
+        assert_eq!(paddle.bounce_angle(400.0, -50.0), -MIN_BOUNCE_ANGLE);
+        assert_eq!(paddle.bounce_angle(400.0, 0.0), 0.0);
+        assert_eq!(paddle.bounce_angle(420.0, 0.0), MAX_BOUNCE_ANGLE / 2.0);
+        assert_eq!(paddle.bounce_angle(460.0, 0.0), MAX_BOUNCE_ANGLE);
+    }
+}
@@ -0,0 +1,160 @@
+use ggez::graphics::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::block::{Block, BlockKind, ROW_COLORS, SOLID_COLOR};
+use crate::power_up::PowerUpKind;
+
+/// Block rows in the high-contrast palette, from the colorblind-safe
+/// Okabe-Ito set, repeating after the last
+const HIGH_CONTRAST_ROWS: [Color; 5] = [
+    Color::new(0.9, 0.62, 0.0, 1.0),
+    Color::new(0.94, 0.89, 0.26, 1.0),
+    Color::new(0.0, 0.62, 0.45, 1.0),
+    Color::new(0.8, 0.47, 0.65, 1.0),
+    Color::new(0.0, 0.45, 0.7, 1.0),
+];
+
+/// `from` at `t` of 0, shading into `to` as `t` goes up to 1
+fn mix(from: Color, to: Color, t: f32) -> Color {
+    Color::new(
+        from.r + (to.r - from.r) * t,
+        from.g + (to.g - from.g) * t,
+        from.b + (to.b - from.b) * t,
+        from.a + (to.a - from.a) * t,
+    )
+}
+
+/// The colors everything in play is drawn in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Palette {
+    /// Red paddle, blue ball and rainbow rows on `GameConfig::background`
+    #[default]
+    Classic,
+    /// Colorblind-safe colors of clearly different lightness on black,
+    /// with every block outlined
+    HighContrast,
+}
+
+impl Palette {
+    pub fn background(self, configured: Color) -> Color {
+        match self {
+            Palette::Classic => configured,
+            Palette::HighContrast => Color::BLACK,
+        }
+    }
+
+    pub fn paddle(self) -> Color {
+        match self {
+            Palette::Classic => Color::RED,
+            Palette::HighContrast => Color::new(0.34, 0.71, 0.91, 1.0),
+        }
+    }
+
+    pub fn ball(self) -> Color {
+        match self {
+            Palette::Classic => Color::BLUE,
+            Palette::HighContrast => Color::WHITE,
+        }
+    }
+
+    pub fn laser(self) -> Color {
+        match self {
+            Palette::Classic => Color::RED,
+            Palette::HighContrast => Color::new(0.84, 0.37, 0.0, 1.0),
+        }
+    }
+
+    /// A ghosting ball, see-through in either palette.
+    pub fn ghost_ball(self) -> Color {
+        match self {
+            Palette::Classic => Color::new(0.5, 0.5, 1.0, 0.5),
+            Palette::HighContrast => Color::new(1.0, 1.0, 1.0, 0.4),
+        }
+    }
+
+    /// The trail behind a ball drawn in `ball`, before it fades with age.
+    pub fn trail(self, ball: Color) -> Color {
+        let alpha = match self {
+            Palette::Classic => 0.5,
+            Palette::HighContrast => 0.7,
+        };
+        Color { a: alpha, ..ball }
+    }
+
+    /// The predicted path of a ball.
+    pub fn trajectory(self) -> Color {
+        match self {
+            Palette::Classic => Color::from_rgb(128, 128, 128),
+            Palette::HighContrast => Color::new(0.7, 0.7, 0.7, 1.0),
+        }
+    }
+
+    /// Score popups, in the `foreground` the HUD uses, or in high contrast
+    /// a yellow that stands out from it.
+    pub fn popup(self, foreground: Color) -> Color {
+        match self {
+            Palette::Classic => foreground,
+            Palette::HighContrast => HIGH_CONTRAST_ROWS[1],
+        }
+    }
+
+    /// A falling power-up, which also tints a ball the SlowBall or FastBall
+    /// it caught is acting on.
+    pub fn power_up(self, kind: PowerUpKind) -> Color {
+        match self {
+            Palette::Classic => match kind {
+                PowerUpKind::WidePaddle => Color::from_rgb(128, 0, 128),
+                PowerUpKind::ExtraLife => Color::from_rgb(255, 105, 180),
+                PowerUpKind::SlowBall => Color::from_rgb(0, 190, 190),
+                PowerUpKind::FastBall => Color::from_rgb(255, 200, 0),
+                PowerUpKind::Split => Color::from_rgb(0, 100, 255),
+                PowerUpKind::Shield => Color::from_rgb(0, 204, 128),
+                PowerUpKind::Ghost => Color::from_rgb(160, 160, 160),
+                PowerUpKind::Sticky => Color::from_rgb(139, 90, 43),
+                PowerUpKind::Laser => Color::from_rgb(255, 60, 0),
+            },
+            // The Okabe-Ito colors again, matching the paddle, shield and
+            // laser where a power-up changes one of them, and grays
+            Palette::HighContrast => match kind {
+                PowerUpKind::WidePaddle => self.paddle(),
+                PowerUpKind::ExtraLife => HIGH_CONTRAST_ROWS[3],
+                PowerUpKind::SlowBall => HIGH_CONTRAST_ROWS[2],
+                PowerUpKind::FastBall => HIGH_CONTRAST_ROWS[0],
+                PowerUpKind::Split => Color::new(0.85, 0.85, 0.85, 1.0),
+                PowerUpKind::Shield => self.shield(),
+                PowerUpKind::Ghost => Color::new(0.55, 0.55, 0.55, 1.0),
+                PowerUpKind::Sticky => HIGH_CONTRAST_ROWS[4],
+                PowerUpKind::Laser => self.laser(),
+            },
+        }
+    }
+
+    pub fn shield(self) -> Color {
+        match self {
+            Palette::Classic => Color::new(0.0, 0.8, 0.5, 1.0),
+            Palette::HighContrast => HIGH_CONTRAST_ROWS[1],
+        }
+    }
+
+    /// A block's color at full strength, before any shading for hits. A
+    /// boss shifts toward the first row's color as its health drops.
+    pub fn block(self, block: &Block) -> Color {
+        let damage = 1.0 - block.health();
+        match (self, block.kind) {
+            (Palette::Classic, BlockKind::Boss) => mix(block.color, ROW_COLORS[0], damage),
+            (Palette::Classic, _) => block.color,
+            (Palette::HighContrast, BlockKind::Boss) => {
+                mix(HIGH_CONTRAST_ROWS[4], HIGH_CONTRAST_ROWS[0], damage)
+            }
+            (Palette::HighContrast, BlockKind::Indestructible) => SOLID_COLOR,
+            (Palette::HighContrast, BlockKind::Normal) => {
+                HIGH_CONTRAST_ROWS[block.row as usize % HIGH_CONTRAST_ROWS.len()]
+            }
+        }
+    }
+
+    /// Whether blocks get an outline so they stand apart by more than color.
+    pub fn outlines_blocks(self) -> bool {
+        self == Palette::HighContrast
+    }
+}
@@ -0,0 +1,101 @@
+use ggez::graphics::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::block::ColorDef;
+use crate::rng::Rng;
+
+/// Particles thrown out by each destroyed block
+const BURST_COUNT: usize = 8;
+/// Seconds a particle lives for
+const LIFETIME: f32 = 0.5;
+/// Range of particle speeds in pixels per second
+const MIN_SPEED: f32 = 60.0;
+const MAX_SPEED: f32 = 180.0;
+/// Side length of the square a particle is drawn as
+pub const PARTICLE_SIZE: f32 = 4.0;
+/// Seconds a score popup stays up, and how fast it rises in pixels per second
+const POPUP_LIFETIME: f32 = 0.8;
+const POPUP_RISE_SPEED: f32 = 40.0;
+
+/// A purely cosmetic fragment of a destroyed block; `x`, `y` is its center.
+#[derive(Serialize, Deserialize)]
+pub struct Particle {
+    pub x: f32,
+    pub y: f32,
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    /// Seconds since the particle was spawned
+    pub age: f32,
+    #[serde(with = "ColorDef")]
+    pub color: Color,
+}
+
+impl Particle {
+    /// A handful of particles flying out from (center_x, center_y) in random directions.
+    pub fn burst(center_x: f32, center_y: f32, color: Color, rng: &mut Rng) -> Vec<Particle> {
+        (0..BURST_COUNT)
+            .map(|_| {
+                let angle = rng.range(0.0, std::f32::consts::TAU);
+                let speed = rng.range(MIN_SPEED, MAX_SPEED);
+                Particle {
+                    x: center_x,
+                    y: center_y,
+                    velocity_x: speed * angle.cos(),
+                    velocity_y: speed * angle.sin(),
+                    age: 0.0,
+                    color,
+                }
+            })
+            .collect()
+    }
+
+    pub fn update_position(&mut self, dt: f32) {
+        self.x += self.velocity_x * dt;
+        self.y += self.velocity_y * dt;
+        self.age += dt;
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.age < LIFETIME
+    }
+
+    /// Fades linearly from opaque to transparent over the particle's lifetime.
+    pub fn alpha(&self) -> f32 {
+        (1.0 - self.age / LIFETIME).clamp(0.0, 1.0)
+    }
+}
+
+/// The points a destroyed block scored, floating up from where it was;
+/// `x`, `y` is the center of the text.
+#[derive(Serialize, Deserialize)]
+pub struct ScorePopup {
+    pub text: String,
+    pub x: f32,
+    pub y: f32,
+    /// Seconds since the popup was spawned
+    pub age: f32,
+}
+
+impl ScorePopup {
+    pub fn new(points: u32, x: f32, y: f32) -> Self {
+        ScorePopup {
+            text: format!("+{}", points),
+            x,
+            y,
+            age: 0.0,
+        }
+    }
+
+    pub fn update_position(&mut self, dt: f32) {
+        self.y -= POPUP_RISE_SPEED * dt;
+        self.age += dt;
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.age < POPUP_LIFETIME
+    }
+
+    pub fn alpha(&self) -> f32 {
+        (1.0 - self.age / POPUP_LIFETIME).clamp(0.0, 1.0)
+    }
+}
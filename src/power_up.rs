@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+use crate::rng::Rng;
+
+/// Side length of the falling power-up square
+pub const POWER_UP_SIZE: f32 = 15.0;
+/// Falling speed in pixels per second
+const FALL_SPEED: f32 = 120.0;
+/// Size of a laser shot, and its speed up the field in pixels per second
+pub const LASER_WIDTH: f32 = 3.0;
+pub const LASER_HEIGHT: f32 = 12.0;
+const LASER_SPEED: f32 = 600.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    WidePaddle,
+    ExtraLife,
+    /// Slows the balls down for a while
+    SlowBall,
+    /// Speeds the balls up for a while
+    FastBall,
+    /// Lets the ball pass through blocks, destroying them, for a while
+    Ghost,
+    /// Balls landing on the paddle stick to it until released, for the
+    /// rest of the life
+    Sticky,
+    /// The paddle can shoot blocks for a while
+    Laser,
+    /// A ball in play splits into three
+    Split,
+    /// A barrier across the bottom bounces the next ball to reach it
+    Shield,
+}
+
+impl PowerUpKind {
+    fn random(rng: &mut Rng) -> Self {
+        match rng.next_u64() % 9 {
+            0 => PowerUpKind::WidePaddle,
+            1 => PowerUpKind::ExtraLife,
+            2 => PowerUpKind::SlowBall,
+            3 => PowerUpKind::Ghost,
+            4 => PowerUpKind::Sticky,
+            5 => PowerUpKind::FastBall,
+            6 => PowerUpKind::Split,
+            7 => PowerUpKind::Shield,
+            _ => PowerUpKind::Laser,
+        }
+    }
+}
+
+/// A power-up square falling from a destroyed block; `x`, `y` is its top-left corner.
+#[derive(Serialize, Deserialize)]
+pub struct PowerUp {
+    pub x: f32,
+    pub y: f32,
+    pub velocity_y: f32,
+    pub kind: PowerUpKind,
+}
+
+impl PowerUp {
+    /// A power-up of a random kind centered on (center_x, center_y).
+    pub fn spawn(center_x: f32, center_y: f32, rng: &mut Rng) -> Self {
+        PowerUp {
+            x: center_x - POWER_UP_SIZE / 2.0,
+            y: center_y - POWER_UP_SIZE / 2.0,
+            velocity_y: FALL_SPEED,
+            kind: PowerUpKind::random(rng),
+        }
+    }
+
+    /// The square's corners as (x1, y1, x2, y2).
+    pub fn rect(&self) -> (f32, f32, f32, f32) {
+        (
+            self.x,
+            self.y,
+            self.x + POWER_UP_SIZE,
+            self.y + POWER_UP_SIZE,
+        )
+    }
+
+    pub fn update_position(&mut self, dt: f32) {
+        self.y += self.velocity_y * dt;
+    }
+}
+
+/// A shot fired up from a laser paddle; `x`, `y` is its top-left corner.
+#[derive(Serialize, Deserialize)]
+pub struct Laser {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Laser {
+    /// A shot centered on `center_x` with its top at `y`.
+    pub fn new(center_x: f32, y: f32) -> Self {
+        Laser {
+            x: center_x - LASER_WIDTH / 2.0,
+            y,
+        }
+    }
+
+    /// The shot's corners as (x1, y1, x2, y2).
+    pub fn rect(&self) -> (f32, f32, f32, f32) {
+        (self.x, self.y, self.x + LASER_WIDTH, self.y + LASER_HEIGHT)
+    }
+
+    pub fn update_position(&mut self, dt: f32) {
+        self.y -= LASER_SPEED * dt;
+    }
+}
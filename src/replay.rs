@@ -0,0 +1,82 @@
+use ggez::{GameError, GameResult};
+use serde::{Deserialize, Serialize};
+
+use crate::config::GameConfig;
+use crate::error::StepError;
+use crate::headless::GameOutcome;
+use crate::paddle::{PaddleAction, PaddleController};
+use crate::state::MainState;
+
+/// A seeded game and the paddle action taken on every tick. The physics is
+/// plain f32 arithmetic on a fixed `TIME_STEP`, so replaying it on the same
+/// build reproduces the run bit for bit; `sin`, `cos` and `hypot` come from
+/// the platform's libm, so other targets may drift.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub config: GameConfig,
+    /// Recorded controller; only matters for whether the ball is served
+    /// automatically, as a `Manual` serve isn't part of the inputs
+    pub controller: PaddleController,
+    pub inputs: Vec<PaddleAction>,
+}
+
+impl Replay {
+    /// Plays a seeded game with `controller` for up to `max_ticks`, keeping
+    /// every decision it made.
+    pub fn record(
+        config: GameConfig,
+        seed: u64,
+        controller: PaddleController,
+        max_ticks: usize,
+    ) -> Result<Self, StepError> {
+        let mut state = MainState::new_seeded(config.clone(), seed);
+        state.controller = controller.build();
+
+        let mut inputs = Vec::new();
+        while state.state.is_running() && inputs.len() < max_ticks {
+            let action = state.decide_paddle_action(state.tick_length());
+            inputs.push(action);
+            state.step_with(action)?;
+        }
+        Ok(Replay {
+            seed,
+            config,
+            controller,
+            inputs,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> GameResult {
+        let json = serde_json::to_string(self).expect("replay always serializes");
+        std::fs::write(path, json).map_err(|e| {
+            GameError::ResourceLoadError(format!("Failed to write replay {}: {}", path, e))
+        })
+    }
+
+    pub fn load(path: &str) -> GameResult<Self> {
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            GameError::ResourceLoadError(format!("Failed to read replay {}: {}", path, e))
+        })?;
+        serde_json::from_str(&json)
+            .map_err(|e| GameError::ResourceLoadError(format!("Invalid replay {}: {}", path, e)))
+    }
+}
+
+impl MainState {
+    /// Re-runs a recorded game from its seed, feeding back the recorded inputs.
+    pub fn replay(replay: &Replay) -> Result<GameOutcome, StepError> {
+        let mut state = MainState::new_seeded(replay.config.clone(), replay.seed);
+        state.controller = replay.controller.build();
+
+        let mut ticks = 0;
+        for &action in &replay.inputs {
+            if !state.state.is_running() {
+                break;
+            }
+            state.step_with(action)?;
+            ticks += 1;
+        }
+        Ok(state.outcome(ticks))
+    }
+}
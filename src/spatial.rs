@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+use crate::block::Block;
+
+/// Side of a grid cell in pixels; a little over two blocks with their gaps
+pub const CELL_SIZE: f32 = 64.0;
+
+/// Block indices bucketed into uniform cells, so a collision test only has
+/// to look at the blocks near the ball. Destroyed blocks stay in their cells
+/// and are skipped by the caller; the grid has to be rebuilt when blocks
+/// are added or moved.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SpatialGrid {
+    origin_x: f32,
+    origin_y: f32,
+    cols: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new(blocks: &[Block]) -> Self {
+        if blocks.is_empty() {
+            return SpatialGrid::default();
+        }
+
+        let origin_x = blocks
+            .iter()
+            .map(|b| b.rect_x1)
+            .fold(f32::INFINITY, f32::min);
+        let origin_y = blocks
+            .iter()
+            .map(|b| b.rect_y1)
+            .fold(f32::INFINITY, f32::min);
+        let max_x = blocks
+            .iter()
+            .map(|b| b.rect_x2)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let max_y = blocks
+            .iter()
+            .map(|b| b.rect_y2)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let cols = ((max_x - origin_x) / CELL_SIZE).floor() as usize + 1;
+        let rows = ((max_y - origin_y) / CELL_SIZE).floor() as usize + 1;
+
+        let mut grid = SpatialGrid {
+            origin_x,
+            origin_y,
+            cols,
+            rows,
+            cells: vec![Vec::new(); cols * rows],
+        };
+        for (index, block) in blocks.iter().enumerate() {
+            let (col1, col2) = grid.col_range(block.rect_x1, block.rect_x2);
+            let (row1, row2) = grid.row_range(block.rect_y1, block.rect_y2);
+            for row in row1..=row2 {
+                for col in col1..=col2 {
+                    grid.cells[row * cols + col].push(index);
+                }
+            }
+        }
+        grid
+    }
+
+    /// Indices of every block sharing a cell with the box from (x1, y1) to
+    /// (x2, y2), in ascending order and without repeats.
+    pub fn candidates(&self, x1: f32, y1: f32, x2: f32, y2: f32) -> Vec<usize> {
+        let mut indices = Vec::new();
+        let outside = x2 < self.origin_x
+            || y2 < self.origin_y
+            || x1 > self.origin_x + self.cols as f32 * CELL_SIZE
+            || y1 > self.origin_y + self.rows as f32 * CELL_SIZE;
+        if self.cells.is_empty() || outside {
+            return indices;
+        }
+
+        let (col1, col2) = self.col_range(x1, x2);
+        let (row1, row2) = self.row_range(y1, y2);
+        for row in row1..=row2 {
+            for col in col1..=col2 {
+                indices.extend_from_slice(&self.cells[row * self.cols + col]);
+            }
+        }
+        // Keep the order of a plain scan over the blocks, so which block
+        // scores or drops a power-up first doesn't depend on the grid
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    fn col_range(&self, x1: f32, x2: f32) -> (usize, usize) {
+        (
+            cell_index(x1, self.origin_x, self.cols),
+            cell_index(x2, self.origin_x, self.cols),
+        )
+    }
+
+    fn row_range(&self, y1: f32, y2: f32) -> (usize, usize) {
+        (
+            cell_index(y1, self.origin_y, self.rows),
+            cell_index(y2, self.origin_y, self.rows),
+        )
+    }
+}
+
+fn cell_index(coordinate: f32, origin: f32, count: usize) -> usize {
+    // A NaN coordinate casts to 0, which still stays in range
+    let cell = ((coordinate - origin) / CELL_SIZE).floor();
+    cell.clamp(0.0, (count - 1) as f32) as usize
+}
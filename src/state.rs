@@ -0,0 +1,2398 @@
+use std::collections::VecDeque;
+
+use ggez::{GameError, GameResult};
+use rust_decimal::prelude::*;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::ball::Ball;
+use crate::block::{
+    format_layout, grid_layout, parse_layout, row_color, Block, BlockKind, BOSS_WIDTH,
+};
+use crate::collision::{
+    check_circle_rectangle_collision, check_circle_rectangle_collision_f32,
+    circle_bounds_overlap_rect, circle_rectangle_contact, rect_intersects_rect, Contact,
+};
+use crate::config::GameConfig;
+use crate::controller::{Controller, GameView};
+use crate::error::StepError;
+use crate::event::{CollisionObject, CollisionRecord, GameEvent};
+use crate::paddle::{Paddle, PaddleAction, PaddleController};
+use crate::particle::{Particle, ScorePopup};
+use crate::power_up::{Laser, PowerUp, PowerUpKind, LASER_HEIGHT};
+use crate::rng::Rng;
+use crate::spatial::SpatialGrid;
+use crate::TIME_STEP;
+
+/// Clearing this level wins the game
+pub const FINAL_LEVEL: usize = 3;
+/// Launch speed multiplier applied on every new level
+const LEVEL_SPEEDUP: f32 = 1.1;
+
+/// WidePaddle grows the paddle by this factor, up to `MAX_PADDLE_WIDTH` of the field
+const WIDE_PADDLE_FACTOR: f32 = 1.5;
+const MAX_PADDLE_WIDTH: f32 = 0.5;
+/// Degrees to either side of the original ball a Split sends its new balls
+const SPLIT_ANGLE: f32 = 30.0;
+
+/// Highest a boss roams, and the share of the field's height its bottom
+/// stays above
+const BOSS_TOP: f32 = 40.0;
+const BOSS_LOWEST: f32 = 0.5;
+
+/// Thickness of the Shield barrier along the bottom of the field
+pub const SHIELD_HEIGHT: f32 = 4.0;
+
+/// Ticks a SlowBall or FastBall lasts for
+const SPEED_EFFECT_DURATION: u32 = 600;
+
+/// Ticks the Ghost power-up lasts for
+const GHOST_DURATION: u32 = 300;
+/// Ticks a ball stays stuck to a sticky paddle before a controller that
+/// serves automatically releases it
+const STICKY_HOLD: u32 = 60;
+
+/// Time scale for the `SLOW_MOTION_DURATION` ticks after the game gets
+/// down to one block or its last life
+const SLOW_MOTION_SCALE: f32 = 0.3;
+const SLOW_MOTION_DURATION: u32 = 120;
+
+/// Ticks the Laser power-up lasts for, and the ticks between shots
+const LASER_DURATION: u32 = 600;
+const LASER_COOLDOWN: u32 = 20;
+
+/// Decimal places kept by `GameConfig::quantize`
+pub const QUANTIZE_PLACES: u32 = 3;
+
+/// Rounds `value` to `QUANTIZE_PLACES` decimal places, leaving values a
+/// Decimal can't hold untouched.
+fn quantize(value: f32) -> f32 {
+    Decimal::from_f32(value)
+        .and_then(|decimal| decimal.round_dp(QUANTIZE_PLACES).to_f32())
+        .unwrap_or(value)
+}
+
+/// Counts another tick of a ball's `velocity` along one axis staying below
+/// `stall_speed`, and once that has gone on for `stall_ticks` in a row,
+/// sets it to `stall_nudge` the way it was going. Returns whether it did.
+fn nudge_if_stalled(ticks: &mut u32, velocity: &mut f32, config: &GameConfig) -> bool {
+    if velocity.abs() >= config.stall_speed {
+        *ticks = 0;
+        return false;
+    }
+    *ticks += 1;
+    if *ticks < config.stall_ticks {
+        return false;
+    }
+    *ticks = 0;
+    let direction = if *velocity > 0.0 { 1.0 } else { -1.0 };
+    *velocity = direction * config.stall_nudge.max(config.stall_speed);
+    true
+}
+
+/// Sends `ball` off `paddle` at `speed`, up for a `direction_y` of -1 or
+/// down for 1, at the paddle's `bounce_angle` for where it landed.
+fn send_off_paddle(paddle: &Paddle, ball: &mut Ball, speed: f32, direction_y: f32) {
+    let angle = paddle.bounce_angle(ball.x, ball.velocity_x).to_radians();
+
+    ball.velocity_x = speed * angle.sin();
+    ball.velocity_y = direction_y * speed * angle.cos();
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameState {
+    /// On the title screen; nothing moves until `start`
+    Menu,
+    /// Placing and removing blocks with `toggle_block_at`; nothing moves
+    /// until `stop_editing`
+    Editing,
+    /// The ball rests on the paddle until it is served
+    Serving,
+    Playing,
+    GameOver,
+    Won,
+}
+
+impl GameState {
+    /// Whether the game is still going, serving or in play.
+    pub fn is_running(self) -> bool {
+        matches!(self, GameState::Serving | GameState::Playing)
+    }
+}
+
+/// What happens to a ball reaching the bottom of the field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BottomBehavior {
+    /// Reflect like the other walls, for practice
+    Bounce,
+    /// Fall out of play, costing a life once no balls are left
+    Lose,
+}
+
+fn no_speed_effect() -> f32 {
+    1.0
+}
+
+/// How a ball found it overlaps a block: which axes to flip, or with
+/// `GameConfig::normal_reflection` the contact to reflect along
+enum BlockHit {
+    Axes((bool, bool)),
+    Contact(Contact),
+}
+
+/// Where a ball hits the paddle
+enum PaddleContact {
+    /// The flat top, aiming the ball by how far off center it lands
+    Top,
+    /// A rounded end, with the normal from `paddle_end_normal`
+    End((f32, f32)),
+    /// A side, the bottom or a corner of a rectangular paddle
+    Face(Contact),
+}
+
+/// The controller a new game starts with.
+pub fn default_controller() -> Box<dyn Controller> {
+    PaddleController::FollowBall.build()
+}
+
+/// Keeps the controller in a snapshot as the `SavedController` it reports,
+/// refusing one that can't be saved rather than restoring a different one.
+mod saved_controller {
+    use serde::de::{Deserialize, Deserializer};
+    use serde::ser::{Error, Serialize, Serializer};
+
+    use crate::controller::{Controller, SavedController};
+
+    #[allow(clippy::borrowed_box)]
+    pub fn serialize<S: Serializer>(
+        controller: &Box<dyn Controller>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        controller
+            .save()
+            .ok_or_else(|| S::Error::custom("the paddle controller can't be saved"))?
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Box<dyn Controller>, D::Error> {
+        SavedController::deserialize(deserializer).map(SavedController::restore)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MainState {
+    /// Settings the game was started with
+    pub config: GameConfig,
+    pub state: GameState,
+    pub lives: u32,
+    pub score: u32,
+    /// Best score of any earlier game, from `load_high_score`
+    #[serde(default)]
+    pub high_score: u32,
+    /// Blocks destroyed since the ball last touched the paddle; each one
+    /// scores its points times the combo it brings the count to
+    pub combo: u32,
+    /// Starts at 1 and goes up to `FINAL_LEVEL`
+    pub level: usize,
+    /// Every ball in play; a life is lost when the last one falls off the bottom
+    pub balls: Vec<Ball>,
+    /// Speed the ball is served at, `GameConfig::launch_speed` sped up
+    /// on every new level
+    pub launch_speed: f32,
+    /// Angle from vertical, in degrees and positive to the right, the ball
+    /// will be served at
+    pub serve_angle: f32,
+    /// Seconds left of `GameConfig::respawn_delay` before the ball can be
+    /// served again
+    #[serde(default)]
+    pub respawn_timer: f32,
+    /// Use the Decimal collision test instead of the faster f32 one
+    pub exact_collisions: bool,
+    pub blocks: Vec<Block>,
+    /// The blocks the game started with, restored by a reset
+    pub first_level: Vec<Block>,
+    /// Buckets `blocks` by position; rebuild with `rebuild_grid` after
+    /// replacing or moving them
+    pub grid: SpatialGrid,
+    pub paddle: Paddle,
+    /// Optional second paddle near the top that deflects rising balls back
+    /// down, added by `enable_top_paddle`
+    pub top_paddle: Option<Paddle>,
+    /// Only a controller that can `save` itself can be put in a snapshot;
+    /// one from before controllers were saved gets `default_controller`
+    #[serde(with = "saved_controller", default = "default_controller")]
+    pub controller: Box<dyn Controller>,
+    pub bottom_wall: BottomBehavior,
+    /// Paddle direction held by the player for the `Manual` controller:
+    /// -1 left, 0 stop, 1 right
+    pub manual_direction: f32,
+    /// Source of every random choice, so a seed replays the same game
+    pub rng: Rng,
+    /// Seed `rng` started from
+    pub seed: u64,
+    /// Draw the launch position and angle from `rng`; otherwise the ball
+    /// always starts from the center heading down and to the right
+    pub random_launch: bool,
+    pub power_ups: Vec<PowerUp>,
+    pub particles: Vec<Particle>,
+    pub popups: Vec<ScorePopup>,
+    /// Drives cosmetic effects, kept apart from `rng` so they never change
+    /// how a seeded game plays out
+    pub effects_rng: Rng,
+    /// While set, `step` leaves the simulation untouched
+    pub paused: bool,
+    /// Multiplies how far everything moves in a tick; drops to
+    /// `SLOW_MOTION_SCALE` in tense moments without touching any velocity
+    pub time_scale: f32,
+    /// Ticks left before `time_scale` goes back to 1
+    #[serde(default)]
+    pub slow_motion_ticks: u32,
+    /// Which tense moments the game is in, as (one block left, last life),
+    /// so each one only slows it down as it begins
+    #[serde(default)]
+    pub tension: (bool, bool),
+    /// Ticks left during which balls go straight through blocks, destroying
+    /// every one they touch
+    pub ghost_ticks: u32,
+    /// What SlowBall and FastBall pickups have scaled the balls' speed by,
+    /// undone once `speed_effect_ticks` runs out
+    #[serde(default = "no_speed_effect")]
+    pub speed_factor: f32,
+    #[serde(default)]
+    pub speed_effect_ticks: u32,
+    /// Balls landing on the paddle stick to it instead of bouncing
+    pub sticky: bool,
+    /// Ticks left during which the paddle can `fire`
+    pub laser_ticks: u32,
+    /// Ticks until the paddle can fire again
+    pub laser_cooldown: u32,
+    pub lasers: Vec<Laser>,
+    /// The Shield barrier, full width along the bottom, while it lasts
+    #[serde(default)]
+    pub shield: Option<Block>,
+    /// Ticks since a ball last stuck to the paddle, while any is stuck
+    pub stuck_ticks: u32,
+    /// Seconds of play so far, counted against `GameConfig::time_limit`
+    pub elapsed: f32,
+    /// Seconds in play since the speed ramp last kicked in
+    pub ramp_elapsed: f32,
+    /// Every ball's center at each of the last `trail_length` ticks in
+    /// play, newest last
+    pub trail: VecDeque<Vec<(f32, f32)>>,
+    /// Physics ticks run since the game started, counting the one in
+    /// progress
+    pub tick: u64,
+    /// Everything that happened since the last `drain_events`, with the
+    /// tick it happened on
+    pub events: Vec<(u64, GameEvent)>,
+    /// Every bounce so far, if `GameConfig::log_collisions` is set
+    pub collisions: Vec<CollisionRecord>,
+}
+
+impl MainState {
+    /// A game on a `rows` by `cols` grid from `config`, which should have
+    /// passed `GameConfig::validate`.
+    pub fn new(config: GameConfig) -> Self {
+        let blocks = grid_layout(
+            config.rows,
+            config.cols,
+            config.block_width,
+            config.block_height,
+            config.fitted_spacing(),
+            config.grid_origin(),
+        );
+        MainState::with_blocks(blocks, config)
+    }
+
+    /// A game on the layout file at `path`, its blocks sized, spaced and
+    /// placed as `config` says, the same cells `toggle_block_at` edits.
+    pub fn from_layout(path: &str, config: GameConfig) -> GameResult<Self> {
+        let layout = std::fs::read_to_string(path).map_err(|e| {
+            GameError::ResourceLoadError(format!("Failed to read layout {}: {}", path, e))
+        })?;
+        let blocks = parse_layout(
+            &layout,
+            config.block_width,
+            config.block_height,
+            config.fitted_spacing(),
+            config.grid_origin(),
+        )
+        .map_err(|e| GameError::ResourceLoadError(format!("Invalid layout {}: {}", path, e)))?;
+        Ok(MainState::with_blocks(blocks, config))
+    }
+
+    /// The default setup playing on an arbitrary set of blocks, which may
+    /// be any size and anywhere in the field.
+    pub fn from_blocks(blocks: Vec<Block>) -> Self {
+        MainState::with_blocks(blocks, GameConfig::default())
+    }
+
+    pub fn with_blocks(blocks: Vec<Block>, config: GameConfig) -> Self {
+        let (width, height) = (config.width, config.height);
+        let paddle = Paddle::new(
+            (width - config.paddle_width) / 2.0,
+            height - 50.0,
+            config.paddle_width,
+            config.paddle_height,
+            config.paddle_speed,
+        );
+        let mut state = MainState {
+            state: GameState::Serving,
+            lives: config.lives,
+            score: 0,
+            high_score: 0,
+            combo: 0,
+            level: 1,
+            balls: Vec::new(),
+            launch_speed: config.launch_speed,
+            serve_angle: 0.0,
+            respawn_timer: 0.0,
+            exact_collisions: false,
+            grid: SpatialGrid::new(&blocks),
+            first_level: blocks.clone(),
+            blocks,
+            paddle,
+            top_paddle: None,
+            controller: default_controller(),
+            bottom_wall: BottomBehavior::Lose,
+            manual_direction: 0.0,
+            rng: Rng::new(0),
+            seed: 0,
+            random_launch: false,
+            power_ups: Vec::new(),
+            particles: Vec::new(),
+            popups: Vec::new(),
+            effects_rng: Rng::new(0),
+            paused: false,
+            time_scale: 1.0,
+            slow_motion_ticks: 0,
+            tension: (false, false),
+            ghost_ticks: 0,
+            speed_factor: 1.0,
+            speed_effect_ticks: 0,
+            sticky: false,
+            laser_ticks: 0,
+            laser_cooldown: 0,
+            lasers: Vec::new(),
+            shield: None,
+            stuck_ticks: 0,
+            elapsed: 0.0,
+            ramp_elapsed: 0.0,
+            trail: VecDeque::new(),
+            tick: 0,
+            events: Vec::new(),
+            collisions: Vec::new(),
+            config,
+        };
+        state.reset_ball();
+        state
+    }
+
+    /// The default layout with every launch drawn from `seed`, so the same
+    /// seed replays the same game.
+    pub fn new_seeded(config: GameConfig, seed: u64) -> Self {
+        let mut state = MainState::new(config);
+        state.random_launch = true;
+        state.reset_with_seed(seed);
+        state
+    }
+
+    /// Restarts the game from the beginning with the same seed, so a seeded
+    /// game plays out the same way again.
+    pub fn reset(&mut self) {
+        self.reset_with_seed(self.seed);
+    }
+
+    /// Starts a fresh game on the first level's blocks, drawing from `seed`.
+    /// The config, the controller and the collision and launch settings
+    /// carry over.
+    pub fn reset_with_seed(&mut self, seed: u64) {
+        let mut fresh = MainState::with_blocks(self.first_level.clone(), self.config.clone());
+        std::mem::swap(&mut fresh.controller, &mut self.controller);
+        fresh.exact_collisions = self.exact_collisions;
+        fresh.bottom_wall = self.bottom_wall;
+        fresh.manual_direction = self.manual_direction;
+        fresh.random_launch = self.random_launch;
+        if self.top_paddle.is_some() {
+            fresh.enable_top_paddle();
+        }
+        fresh.rng = Rng::new(seed);
+        fresh.effects_rng = Rng::new(seed);
+        fresh.seed = seed;
+        fresh.high_score = self.high_score;
+        fresh.reset_ball();
+        *self = fresh;
+    }
+
+    /// Replaces every ball with a single one resting on the paddle, waiting
+    /// to be served.
+    fn reset_ball(&mut self) {
+        // Up and to the right at 45 degrees, or 30-60 degrees to either side
+        self.serve_angle = 45.0;
+        if self.random_launch {
+            let side = if self.rng.next_f32() < 0.5 { -1.0 } else { 1.0 };
+            self.serve_angle = side * self.rng.range(30.0, 60.0);
+        }
+
+        self.balls = vec![Ball::new(0.0, 0.0, 0.0, 0.0)];
+        self.combo = 0;
+        self.sticky = false;
+        self.stuck_ticks = 0;
+        self.laser_ticks = 0;
+        self.speed_factor = 1.0;
+        self.speed_effect_ticks = 0;
+        self.lasers.clear();
+        self.shield = None;
+        self.trail.clear();
+        self.respawn_timer = 0.0;
+        self.hold_ball_on_paddle();
+        self.state = GameState::Serving;
+    }
+
+    fn hold_ball_on_paddle(&mut self) {
+        let x = self.paddle.x + self.paddle.width / 2.0;
+        let y = self.paddle.y - self.config.ball_radius;
+        for ball in &mut self.balls {
+            ball.x = x;
+            ball.y = y;
+        }
+    }
+
+    /// Whether the ball is waiting on the paddle and the respawn delay is over.
+    pub fn can_serve(&self) -> bool {
+        self.state == GameState::Serving && self.respawn_timer <= 0.0
+    }
+
+    /// Launches the ball off the paddle at `serve_angle` if `can_serve`.
+    pub fn serve(&mut self) {
+        if !self.can_serve() {
+            return;
+        }
+        let angle = self.serve_angle.to_radians();
+        let speed = self.serve_speed();
+        for ball in &mut self.balls {
+            ball.velocity_x = speed * angle.sin();
+            ball.velocity_y = -speed * angle.cos();
+        }
+        self.state = GameState::Playing;
+    }
+
+    /// Seconds left before the time limit runs out, if there is one.
+    pub fn time_left(&self) -> Option<f32> {
+        self.config
+            .time_limit
+            .map(|limit| (limit.as_secs_f32() - self.elapsed).max(0.0))
+    }
+
+    pub fn has_stuck_balls(&self) -> bool {
+        self.balls.iter().any(|ball| ball.stuck_offset.is_some())
+    }
+
+    /// Speed a ball leaves the paddle at when served or released:
+    /// `launch_speed` with any running speed effect, so the effect comes
+    /// off a ball that was waiting when it was caught like any other.
+    fn serve_speed(&self) -> f32 {
+        self.launch_speed * self.speed_factor
+    }
+
+    /// Launches every ball stuck to the paddle at the serve speed.
+    pub fn release(&mut self) {
+        let speed = self.serve_speed();
+        let mut balls = std::mem::take(&mut self.balls);
+        for ball in &mut balls {
+            if ball.stuck_offset.take().is_some() {
+                send_off_paddle(&self.paddle, ball, speed, -1.0);
+            }
+        }
+        self.balls = balls;
+        self.stuck_ticks = 0;
+    }
+
+    /// Leaves the title screen for the first serve.
+    pub fn start(&mut self) {
+        if self.state == GameState::Menu {
+            self.state = GameState::Serving;
+        }
+    }
+
+    /// Stops the game to edit its blocks, with the ball back on the paddle.
+    pub fn start_editing(&mut self) {
+        self.reset_ball();
+        self.state = GameState::Editing;
+    }
+
+    /// Starts a fresh game on the edited blocks, which become the first level.
+    pub fn stop_editing(&mut self) {
+        if self.state == GameState::Editing {
+            self.blocks.retain(|block| block.is_visible);
+            self.first_level = self.blocks.clone();
+            self.reset();
+        }
+    }
+
+    /// Removes the block in the grid cell under (`x`, `y`), or places a
+    /// fresh one there if the cell is empty. Cells are laid out as in
+    /// `grid_layout` from `grid_origin`, and only those right of and below
+    /// it and clear of the paddle can be edited.
+    pub fn toggle_block_at(&mut self, x: f32, y: f32) {
+        let config = &self.config;
+        let gap = config.fitted_spacing();
+        let (step_x, step_y) = (config.block_width + gap, config.block_height + gap);
+        let (origin_x, origin_y) = config.grid_origin();
+        if x < origin_x || y < origin_y {
+            return;
+        }
+        let col = ((x - origin_x) / step_x) as u32;
+        let row = ((y - origin_y) / step_y) as u32;
+        let (x1, y1) = (
+            origin_x + col as f32 * step_x,
+            origin_y + row as f32 * step_y,
+        );
+        if x1 + config.block_width > self.config.width || y1 + config.block_height > self.paddle.y {
+            return;
+        }
+        let existing = self
+            .blocks
+            .iter()
+            .position(|block| block.is_visible && block.row == row && block.col == col);
+        match existing {
+            Some(index) => {
+                self.blocks.remove(index);
+            }
+            None => {
+                let points = config.rows.saturating_sub(row).max(1) * 10;
+                self.blocks.push(Block {
+                    row,
+                    col,
+                    color: row_color(row),
+                    ..Block::new(x1, y1, config.block_width, config.block_height, points, 1)
+                });
+            }
+        }
+        self.rebuild_grid();
+    }
+
+    /// Writes the standing blocks to `path` in the format `from_layout` loads.
+    pub fn save_layout(&self, path: &str) -> GameResult {
+        if self.blocks_remaining() == 0 {
+            return Err(GameError::ConfigError(
+                "Nothing to save: the layout has no breakable blocks".to_string(),
+            ));
+        }
+        std::fs::write(path, format_layout(&self.blocks)).map_err(|e| {
+            GameError::ResourceLoadError(format!("Failed to write layout {}: {}", path, e))
+        })
+    }
+
+    /// Snapshot of the whole simulation, controller included, that
+    /// `from_json` restores to carry on exactly where it left off. Fails
+    /// for a controller that can't `save` itself.
+    pub fn to_json(&self) -> GameResult<String> {
+        serde_json::to_string(self)
+            .map_err(|e| GameError::CustomError(format!("Can't snapshot the game: {}", e)))
+    }
+
+    pub fn from_json(json: &str) -> GameResult<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| GameError::ResourceLoadError(format!("Invalid snapshot: {}", e)))
+    }
+
+    pub fn rebuild_grid(&mut self) {
+        self.grid = SpatialGrid::new(&self.blocks);
+    }
+
+    /// Hands over the events collected so far and starts a fresh list.
+    pub fn drain_events(&mut self) -> Vec<(u64, GameEvent)> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn collision_log(&self) -> &[CollisionRecord] {
+        &self.collisions
+    }
+
+    fn log_collision(
+        &mut self,
+        object: CollisionObject,
+        block_index: Option<usize>,
+        velocity_before: (f32, f32),
+        ball: &Ball,
+    ) {
+        if self.config.log_collisions {
+            self.collisions.push(CollisionRecord {
+                tick: self.tick,
+                object,
+                block_index,
+                velocity_before,
+                velocity_after: (ball.velocity_x, ball.velocity_y),
+            });
+        }
+    }
+
+    pub fn current_tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn current_score(&self) -> u32 {
+        self.score
+    }
+
+    /// The high score, or this game's score once it beats it.
+    pub fn best_score(&self) -> u32 {
+        self.high_score.max(self.score)
+    }
+
+    /// Reads the high score saved at `path`, counting a missing or
+    /// unreadable file as no high score yet.
+    pub fn load_high_score(&mut self, path: &str) {
+        self.high_score = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|text| text.trim().parse().ok())
+            .unwrap_or(0);
+    }
+
+    /// Writes this game's score to `path` if it beats the high score,
+    /// returning whether it did.
+    pub fn save_high_score(&mut self, path: &str) -> GameResult<bool> {
+        if self.score <= self.high_score {
+            return Ok(false);
+        }
+        self.high_score = self.score;
+        std::fs::write(path, format!("{}\n", self.score)).map_err(|e| {
+            GameError::ResourceLoadError(format!("Failed to write high score {}: {}", path, e))
+        })?;
+        Ok(true)
+    }
+
+    pub fn current_combo(&self) -> u32 {
+        self.combo
+    }
+
+    pub fn current_level(&self) -> usize {
+        self.level
+    }
+
+    /// Moves on to the next level: a fresh grid with one more row and a
+    /// faster serve.
+    pub fn next_level(&mut self) {
+        self.level += 1;
+        self.blocks = if self.level == FINAL_LEVEL {
+            vec![Block::boss(
+                (self.config.width - BOSS_WIDTH) / 2.0,
+                BOSS_TOP,
+            )]
+        } else {
+            grid_layout(
+                self.config.rows + self.level as u32 - 1,
+                self.config.cols,
+                self.config.block_width,
+                self.config.block_height,
+                self.config.fitted_spacing(),
+                self.config.grid_origin(),
+            )
+        };
+        self.rebuild_grid();
+        self.power_ups.clear();
+        self.launch_speed *= LEVEL_SPEEDUP;
+        self.reset_ball();
+    }
+
+    /// What the controller sees of the game for a tick of `dt` seconds.
+    pub fn view(&self, dt: f32) -> GameView<'_> {
+        GameView {
+            balls: &self.balls,
+            paddle: &self.paddle,
+            blocks: &self.blocks,
+            width: self.config.width,
+            radius: self.config.ball_radius,
+            manual_direction: self.manual_direction,
+            dt,
+        }
+    }
+
+    /// Asks the controller what to do with the paddle this tick.
+    pub fn decide_paddle_action(&mut self, dt: f32) -> PaddleAction {
+        self.adapt_paddle_speed();
+        // Built from the fields directly rather than with `view`, so the
+        // controller can be borrowed mutably at the same time
+        let view = GameView {
+            balls: &self.balls,
+            paddle: &self.paddle,
+            blocks: &self.blocks,
+            width: self.config.width,
+            radius: self.config.ball_radius,
+            manual_direction: self.manual_direction,
+            dt,
+        };
+        self.controller.decide(&view)
+    }
+
+    /// Sets the paddle's speed for this tick: the configured speed, or for a
+    /// controller that adapts it, enough to keep up with the tracked ball.
+    fn adapt_paddle_speed(&mut self) {
+        let base = self.config.paddle_speed;
+        self.paddle.speed = base;
+        if !self.controller.adapts_paddle_speed() {
+            return;
+        }
+        let ball_speed = self
+            .view(TIME_STEP)
+            .tracked_ball()
+            .map_or(0.0, |ball| ball.speed());
+        let adapted =
+            (ball_speed * self.config.ai_speed_factor).min(self.config.max_ai_paddle_speed);
+        if adapted > base {
+            self.paddle.speed = adapted;
+        }
+    }
+
+    /// Sends the ball back up at an angle set by where it hit the paddle:
+    /// `MIN_BOUNCE_ANGLE` from vertical near the center, up to
+    /// `MAX_BOUNCE_ANGLE` at the ends. The ball keeps its speed.
+    fn reflect_off_paddle(&self, ball: &mut Ball) {
+        send_off_paddle(&self.paddle, ball, ball.speed(), -1.0);
+    }
+
+    /// How `ball` is hitting the paddle, if it is. Only a descending ball
+    /// bounces off the flat top, so one already on its way up can't be
+    /// caught again while it is still over the paddle; the rounded ends
+    /// take any ball moving into them. With `paddle_rect_collision` the
+    /// paddle is a plain rectangle instead, whose sides and corners
+    /// reflect the ball along their normal.
+    fn paddle_contact(&self, ball: &Ball) -> Option<PaddleContact> {
+        if self.config.paddle_rect_collision {
+            let contact = circle_rectangle_contact(
+                ball.x,
+                ball.y,
+                self.config.ball_radius,
+                self.paddle.rect(),
+            )?;
+            let (normal_x, normal_y) = contact.normal;
+            if ball.velocity_x * normal_x + ball.velocity_y * normal_y >= 0.0 {
+                return None;
+            }
+            return Some(if normal_x == 0.0 && normal_y < 0.0 {
+                PaddleContact::Top
+            } else {
+                PaddleContact::Face(contact)
+            });
+        }
+        if let Some(normal) = self.paddle_end_normal(ball) {
+            return Some(PaddleContact::End(normal));
+        }
+        let on_top = self.paddle.end_center(ball.x).is_none()
+            && ball.velocity_y > 0.0
+            && ball.y + self.config.ball_radius >= self.paddle.y;
+        on_top.then_some(PaddleContact::Top)
+    }
+
+    /// Where `ball` touches one of the paddle's rounded ends while moving
+    /// into it, as the unit normal pointing from the end toward the ball.
+    fn paddle_end_normal(&self, ball: &Ball) -> Option<(f32, f32)> {
+        let (center_x, center_y) = self.paddle.end_center(ball.x)?;
+        let (dx, dy) = (ball.x - center_x, ball.y - center_y);
+        let distance = dx.hypot(dy);
+        if distance == 0.0 || distance > self.config.ball_radius + self.paddle.end_radius() {
+            return None;
+        }
+        let normal = (dx / distance, dy / distance);
+        (ball.velocity_x * normal.0 + ball.velocity_y * normal.1 < 0.0).then_some(normal)
+    }
+
+    /// Bounces `ball` off a rounded end of the paddle. It mirrors about the
+    /// end's `normal`, unless that is less of an angle than a hit at the
+    /// very end of the flat part would give, and is pushed clear of the end.
+    fn round_off_paddle_end(&self, ball: &mut Ball, normal: (f32, f32)) {
+        let (vx, vy) = (ball.velocity_x, ball.velocity_y);
+        let dot = vx * normal.0 + vy * normal.1;
+        let reflected = (vx - 2.0 * dot * normal.0, vy - 2.0 * dot * normal.1);
+        send_off_paddle(&self.paddle, ball, ball.speed(), -1.0);
+        if reflected.0.abs() > ball.velocity_x.abs() {
+            ball.velocity_x = reflected.0;
+            ball.velocity_y = reflected.1;
+        }
+        if let Some((center_x, center_y)) = self.paddle.end_center(ball.x) {
+            let reach = self.config.ball_radius + self.paddle.end_radius();
+            ball.x = center_x + normal.0 * reach;
+            ball.y = center_y + normal.1 * reach;
+        }
+    }
+
+    /// Adds a second paddle near the top of the field, facing the bottom
+    /// one, that chases rising balls.
+    pub fn enable_top_paddle(&mut self) {
+        let paddle = &self.paddle;
+        let mut top = Paddle::new(paddle.x, 0.0, paddle.width, paddle.height, paddle.speed);
+        top.y = self.config.height - paddle.y - paddle.height;
+        self.top_paddle = Some(top);
+    }
+
+    /// Moves the top paddle under the highest rising ball.
+    fn move_top_paddle(&mut self, dt: f32) {
+        let Some(top) = &mut self.top_paddle else {
+            return;
+        };
+        let rising = self
+            .balls
+            .iter()
+            .filter(|ball| ball.velocity_y < 0.0)
+            .min_by(|a, b| a.y.total_cmp(&b.y));
+        let action = match rising {
+            Some(ball) => top.action_toward(ball.x, dt),
+            None => PaddleAction::Stay,
+        };
+        top.move_by(action.direction(), self.config.width, dt);
+    }
+
+    /// Moves the falling power-ups, removing the ones that fell off the
+    /// bottom and returning the kinds of those the paddle caught.
+    fn catch_power_ups(&mut self, dt: f32) -> Vec<PowerUpKind> {
+        let mut caught = Vec::new();
+        let paddle = self.paddle.rect();
+        let height = self.config.height;
+        self.power_ups.retain_mut(|power_up| {
+            power_up.update_position(dt);
+            if rect_intersects_rect(power_up.rect(), paddle) {
+                caught.push(power_up.kind);
+                return false;
+            }
+            power_up.y <= height
+        });
+        caught
+    }
+
+    /// Speeds every ball up by `speed_ramp` each time another
+    /// `speed_ramp_interval` seconds have passed, keeping its direction.
+    fn ramp_up_speed(&mut self, dt: f32) {
+        if self.config.speed_ramp <= 0.0 || self.config.speed_ramp_interval <= 0.0 {
+            return;
+        }
+        self.ramp_elapsed += dt;
+        while self.ramp_elapsed >= self.config.speed_ramp_interval {
+            self.ramp_elapsed -= self.config.speed_ramp_interval;
+            let factor = 1.0 + self.config.speed_ramp;
+            for ball in &mut self.balls {
+                ball.velocity_x *= factor;
+                ball.velocity_y *= factor;
+                ball.clamp_speed(self.config.max_speed);
+            }
+        }
+    }
+
+    /// Counts the ticks `ball` has been moving almost horizontally or almost
+    /// vertically, kicking it up or down, or to the side, once either has
+    /// gone on for `stall_ticks`.
+    fn break_stall(&self, ball: &mut Ball) {
+        let config = &self.config;
+        if config.stall_ticks == 0 {
+            ball.stalled_ticks = 0;
+            ball.vertical_stall_ticks = 0;
+            return;
+        }
+        let kicked_y = nudge_if_stalled(&mut ball.stalled_ticks, &mut ball.velocity_y, config);
+        let kicked_x =
+            nudge_if_stalled(&mut ball.vertical_stall_ticks, &mut ball.velocity_x, config);
+        if kicked_x || kicked_y {
+            ball.clamp_speed(config.max_speed);
+        }
+    }
+
+    fn update_particles(&mut self, dt: f32) {
+        self.particles.retain_mut(|particle| {
+            particle.update_position(dt);
+            particle.is_alive()
+        });
+        self.popups.retain_mut(|popup| {
+            popup.update_position(dt);
+            popup.is_alive()
+        });
+    }
+
+    fn apply_power_up(&mut self, kind: PowerUpKind) {
+        match kind {
+            PowerUpKind::WidePaddle => {
+                let center = self.paddle.x + self.paddle.width / 2.0;
+                let width = (self.paddle.width * WIDE_PADDLE_FACTOR)
+                    .min(self.config.width * MAX_PADDLE_WIDTH)
+                    .max(self.paddle.width);
+                self.paddle.width = width;
+                self.paddle.x =
+                    (center - width / 2.0).clamp(0.0, (self.config.width - width).max(0.0));
+            }
+            PowerUpKind::ExtraLife => self.lives += 1,
+            PowerUpKind::Ghost => self.ghost_ticks = GHOST_DURATION,
+            PowerUpKind::Sticky => self.sticky = true,
+            PowerUpKind::Laser => self.laser_ticks = LASER_DURATION,
+            PowerUpKind::SlowBall => self.add_speed_effect(self.config.slow_ball_factor),
+            PowerUpKind::FastBall => self.add_speed_effect(self.config.fast_ball_factor),
+            PowerUpKind::Split => self.split_ball(),
+            PowerUpKind::Shield => {
+                let y = self.config.height - SHIELD_HEIGHT;
+                self.shield = Some(Block::new(0.0, y, self.config.width, SHIELD_HEIGHT, 0, 1));
+            }
+        }
+    }
+
+    /// Adds two balls leaving the first moving one at `SPLIT_ANGLE` to
+    /// either side of it, as far as `max_balls` allows.
+    fn split_ball(&mut self) {
+        let Some(ball) = self
+            .balls
+            .iter()
+            .find(|ball| ball.stuck_offset.is_none() && ball.speed() > 0.0)
+        else {
+            return;
+        };
+        let room = self.config.max_balls.saturating_sub(self.balls.len());
+        let split: Vec<Ball> = [-SPLIT_ANGLE, SPLIT_ANGLE]
+            .iter()
+            .take(room)
+            .map(|&angle| ball.rotated(angle))
+            .collect();
+        self.balls.extend(split);
+    }
+
+    /// Scales the balls' speed by `factor` on top of any speed effect
+    /// already running, restarting its clock. The total is kept to what
+    /// takes a ball served at `launch_speed` between `min_speed` and
+    /// `max_speed`.
+    fn add_speed_effect(&mut self, factor: f32) {
+        let total = (self.speed_factor * factor).clamp(
+            self.config.min_speed / self.launch_speed,
+            self.config.max_speed / self.launch_speed,
+        );
+        self.scale_ball_speed(total / self.speed_factor);
+        self.speed_factor = total;
+        self.speed_effect_ticks = SPEED_EFFECT_DURATION;
+    }
+
+    /// Counts down the running speed effect, undoing it at the end.
+    fn update_speed_effect(&mut self) {
+        if self.speed_effect_ticks == 0 {
+            return;
+        }
+        self.speed_effect_ticks -= 1;
+        if self.speed_effect_ticks == 0 {
+            self.scale_ball_speed(1.0 / self.speed_factor);
+            self.speed_factor = 1.0;
+        }
+    }
+
+    fn scale_ball_speed(&mut self, factor: f32) {
+        for ball in &mut self.balls {
+            ball.velocity_x *= factor;
+            ball.velocity_y *= factor;
+            ball.clamp_speed(self.config.max_speed);
+            ball.keep_speed_above(self.config.min_speed);
+        }
+    }
+
+    fn decimal_ball(&self, ball: &Ball) -> Option<(Decimal, Decimal, Decimal)> {
+        if !self.exact_collisions {
+            return None;
+        }
+        Some((
+            Decimal::from_f32(ball.x)?,
+            Decimal::from_f32(ball.y)?,
+            Decimal::from_f32(self.config.ball_radius)?,
+        ))
+    }
+
+    fn sub_steps(&self, ball: &Ball, dt: f32) -> u32 {
+        let distance = ball.speed() * dt;
+        if !distance.is_finite() {
+            return 1;
+        }
+        ((distance / self.config.ball_radius).ceil() as u32).max(1)
+    }
+
+    /// Slides the moving blocks, re-indexing them in the grid if any moved.
+    fn move_blocks(&mut self, dt: f32) {
+        let mut moved = false;
+        for block in &mut self.blocks {
+            if !block.is_visible || block.velocity_x == 0.0 {
+                continue;
+            }
+            if block.kind == BlockKind::Boss {
+                let range = (BOSS_TOP, self.config.height * BOSS_LOWEST);
+                block.update_boss_position(self.config.width, range, dt);
+            } else {
+                block.update_position(self.config.width, dt);
+            }
+            moved = true;
+        }
+        if moved {
+            self.rebuild_grid();
+        }
+    }
+
+    /// Bounces every pair of touching balls off each other, leaving balls
+    /// stuck to the paddle where they are.
+    fn collide_balls(&mut self) {
+        let mut records = Vec::new();
+        for i in 1..self.balls.len() {
+            let (before, after) = self.balls.split_at_mut(i);
+            let ball = &mut after[0];
+            for other in before {
+                if ball.stuck_offset.is_some() || other.stuck_offset.is_some() {
+                    continue;
+                }
+                let velocities = [
+                    (ball.velocity_x, ball.velocity_y),
+                    (other.velocity_x, other.velocity_y),
+                ];
+                if ball.collide_with(other, self.config.ball_radius) && self.config.log_collisions {
+                    for (moved, velocity_before) in [&*ball, &*other].into_iter().zip(velocities) {
+                        records.push(CollisionRecord {
+                            tick: self.tick,
+                            object: CollisionObject::Ball,
+                            block_index: None,
+                            velocity_before,
+                            velocity_after: (moved.velocity_x, moved.velocity_y),
+                        });
+                    }
+                }
+            }
+        }
+        self.collisions.extend(records);
+    }
+
+    fn record_trail(&mut self) {
+        if self.config.trail_length == 0 {
+            return;
+        }
+        while self.trail.len() >= self.config.trail_length {
+            self.trail.pop_front();
+        }
+        self.trail
+            .push_back(self.balls.iter().map(|ball| (ball.x, ball.y)).collect());
+    }
+
+    /// Whether breaking the last boss clears the level, or with no boss,
+    /// breaking every breakable block.
+    pub fn level_cleared(&self) -> bool {
+        let mut bosses = self.bosses().peekable();
+        if bosses.peek().is_some() {
+            bosses.all(|boss| !boss.is_visible)
+        } else {
+            self.blocks_remaining() == 0
+        }
+    }
+
+    /// Whether one more hit could clear the level: a boss on its last
+    /// hit, or with no boss, a single block left.
+    fn nearly_cleared(&self) -> bool {
+        if self.bosses().next().is_some() {
+            let mut standing = self.bosses().filter(|boss| boss.is_visible);
+            match (standing.next(), standing.next()) {
+                (Some(boss), None) => boss.hits_remaining <= 1,
+                _ => false,
+            }
+        } else {
+            self.blocks_remaining() == 1
+        }
+    }
+
+    fn bosses(&self) -> impl Iterator<Item = &Block> {
+        self.blocks
+            .iter()
+            .filter(|block| block.kind == BlockKind::Boss)
+    }
+
+    /// Breakable blocks still standing; without a boss the level is
+    /// cleared at zero.
+    pub fn blocks_remaining(&self) -> usize {
+        self.blocks
+            .iter()
+            .filter(|block| block.is_visible && block.is_breakable())
+            .count()
+    }
+
+    fn lose_life(&mut self) {
+        self.events.push((self.tick, GameEvent::LifeLost));
+        self.lives = self.lives.saturating_sub(1);
+        if self.lives == 0 {
+            self.state = GameState::GameOver;
+        } else {
+            self.reset_ball();
+            self.respawn_timer = self.config.respawn_delay;
+        }
+    }
+
+    /// Moves one ball through a tick, bouncing it off the walls, the paddle
+    /// and the blocks. Returns false once it has fallen off the bottom.
+    fn step_ball(&mut self, ball: &mut Ball, dt: f32) -> bool {
+        if let Some(offset) = ball.stuck_offset {
+            ball.x = self.paddle.x + offset;
+            ball.y = self.paddle.y - self.config.ball_radius;
+            return true;
+        }
+        self.break_stall(ball);
+        // Only the vertical speed changes, so bounces still keep the
+        // horizontal speed they had
+        ball.velocity_y += self.config.gravity * dt;
+
+        // Move the ball in sub-steps no longer than its radius so a fast ball
+        // can't skip over a block between two ticks
+        let steps = self.sub_steps(ball, dt);
+        let step_dt = dt / steps as f32;
+        for _ in 0..steps {
+            ball.x += ball.velocity_x * step_dt;
+            ball.y += ball.velocity_y * step_dt;
+            let before = (ball.velocity_x, ball.velocity_y);
+
+            // Only reflect off a wall the ball is moving into, so a ball that
+            // starts overlapping one moves away instead of vibrating against it
+            let hits_left = ball.x - self.config.ball_radius <= 0.0 && ball.velocity_x < 0.0;
+            let hits_right =
+                ball.x + self.config.ball_radius >= self.config.width && ball.velocity_x > 0.0;
+            let hits_top = ball.y - self.config.ball_radius <= 0.0 && ball.velocity_y < 0.0;
+            let hits_bottom = self.bottom_wall == BottomBehavior::Bounce
+                && ball.y + self.config.ball_radius >= self.config.height
+                && ball.velocity_y > 0.0;
+            let restitution = self.config.restitution;
+            if hits_left || hits_right {
+                ball.velocity_x = -ball.velocity_x * restitution;
+                self.events.push((self.tick, GameEvent::WallBounce));
+            }
+            if hits_top || hits_bottom {
+                ball.velocity_y = -ball.velocity_y * restitution;
+                self.events.push((self.tick, GameEvent::WallBounce));
+            }
+            if hits_left || hits_right || hits_top || hits_bottom {
+                if self.config.wall_jitter > 0.0 {
+                    let jitter = self.config.wall_jitter;
+                    ball.rotate(self.rng.range(-jitter, jitter));
+                }
+                ball.keep_speed_above(self.config.min_speed);
+                self.log_collision(CollisionObject::Wall, None, before, ball);
+            }
+            // The shield takes the drop instead, wearing out as it does
+            if let Some(shield) = &mut self.shield {
+                if ball.velocity_y > 0.0 && ball.y + self.config.ball_radius >= shield.rect_y1 {
+                    ball.velocity_y = -ball.velocity_y;
+                    ball.y = shield.rect_y1 - self.config.ball_radius;
+                    shield.hits_remaining = shield.hits_remaining.saturating_sub(1);
+                    if shield.hits_remaining == 0 {
+                        self.shield = None;
+                    }
+                    self.events.push((self.tick, GameEvent::WallBounce));
+                    self.log_collision(CollisionObject::Wall, None, before, ball);
+                }
+            }
+            if self.bottom_wall == BottomBehavior::Lose
+                && ball.y - self.config.ball_radius > self.config.height
+            {
+                self.events.push((self.tick, GameEvent::BallLost));
+                return false;
+            }
+
+            if let Some(contact) = self.paddle_contact(ball) {
+                self.events.push((self.tick, GameEvent::PaddleHit));
+                self.combo = 0;
+                // Only a ball landing on top can stick
+                if self.sticky && !matches!(contact, PaddleContact::Face(_)) {
+                    ball.velocity_x = 0.0;
+                    ball.velocity_y = 0.0;
+                    ball.y = self.paddle.y - self.config.ball_radius;
+                    ball.stuck_offset =
+                        Some((ball.x - self.paddle.x).clamp(0.0, self.paddle.width));
+                    self.stuck_ticks = 0;
+                    self.log_collision(CollisionObject::Paddle, None, before, ball);
+                    return true;
+                }
+                match contact {
+                    PaddleContact::Top => self.reflect_off_paddle(ball),
+                    PaddleContact::End(normal) => self.round_off_paddle_end(ball, normal),
+                    PaddleContact::Face(contact) => {
+                        let (normal_x, normal_y) = contact.normal;
+                        let dot = ball.velocity_x * normal_x + ball.velocity_y * normal_y;
+                        ball.velocity_x -= 2.0 * dot * normal_x;
+                        ball.velocity_y -= 2.0 * dot * normal_y;
+                        ball.x += normal_x * contact.depth;
+                        ball.y += normal_y * contact.depth;
+                    }
+                }
+                ball.clamp_speed(self.config.max_speed);
+                self.log_collision(CollisionObject::Paddle, None, before, ball);
+            }
+            let top_before = (ball.velocity_x, ball.velocity_y);
+            let hits_top_paddle = self.top_paddle.as_ref().is_some_and(|top| {
+                ball.velocity_y < 0.0
+                    && ball.y - self.config.ball_radius <= top.y + top.height
+                    && ball.x >= top.x
+                    && ball.x <= top.x + top.width
+            });
+            if let (true, Some(top)) = (hits_top_paddle, &self.top_paddle) {
+                send_off_paddle(top, ball, ball.speed(), 1.0);
+                ball.clamp_speed(self.config.max_speed);
+                self.events.push((self.tick, GameEvent::PaddleHit));
+                self.combo = 0;
+                self.log_collision(CollisionObject::Paddle, None, top_before, ball);
+            }
+
+            // Gather every block hit this step first, so a ball straddling two
+            // blocks is reflected once instead of flipping back and forth
+            let mut flip_x = false;
+            let mut flip_y = false;
+            let mut normal: (f32, f32) = (0.0, 0.0);
+            let mut hit_blocks = Vec::new();
+            let before = (ball.velocity_x, ball.velocity_y);
+            // A NaN or infinite ball position can't be converted to Decimal;
+            // exact collisions are skipped for this step instead of panicking
+            let decimal_ball = self.decimal_ball(ball);
+            let candidates = self.grid.candidates(
+                ball.x - self.config.ball_radius,
+                ball.y - self.config.ball_radius,
+                ball.x + self.config.ball_radius,
+                ball.y + self.config.ball_radius,
+            );
+            for index in candidates {
+                let block = &mut self.blocks[index];
+                if !block.is_visible
+                    || !circle_bounds_overlap_rect(
+                        ball.x,
+                        ball.y,
+                        self.config.ball_radius,
+                        (block.rect_x1, block.rect_y1, block.rect_x2, block.rect_y2),
+                    )
+                {
+                    continue;
+                }
+                let rect = (block.rect_x1, block.rect_y1, block.rect_x2, block.rect_y2);
+                let hit = if self.config.normal_reflection {
+                    circle_rectangle_contact(ball.x, ball.y, self.config.ball_radius, rect)
+                        .map(BlockHit::Contact)
+                } else if self.exact_collisions {
+                    match (decimal_ball, block.decimal_rect()) {
+                        (Some((circle_x, circle_y, radius)), Some((x1, y1, x2, y2))) => {
+                            check_circle_rectangle_collision(
+                                circle_x, circle_y, radius, x1, y1, x2, y2,
+                            )
+                        }
+                        _ => None,
+                    }
+                    .map(BlockHit::Axes)
+                } else {
+                    check_circle_rectangle_collision_f32(
+                        ball.x,
+                        ball.y,
+                        self.config.ball_radius,
+                        block.rect_x1,
+                        block.rect_y1,
+                        block.rect_x2,
+                        block.rect_y2,
+                    )
+                    .map(BlockHit::Axes)
+                };
+                let Some(hit) = hit else {
+                    continue;
+                };
+                // Even a ghosting ball bounces off indestructible blocks,
+                // and only takes one hit off a boss
+                if self.ghost_ticks > 0 && block.is_breakable() && block.kind != BlockKind::Boss {
+                    block.hits_remaining = 0;
+                } else {
+                    match hit {
+                        BlockHit::Axes((collision_x, collision_y)) => {
+                            // Only reflect off a face the ball is moving into. If
+                            // the nearest face is one it is leaving, it really
+                            // came in through the other axis; if it is leaving
+                            // both, it isn't hitting the block at all
+                            let (into_x, into_y) = ball.moving_into(block, self.config.ball_radius);
+                            let along_x = into_x && (collision_x || !into_y);
+                            let along_y = into_y && (collision_y || !into_x);
+                            if !along_x && !along_y {
+                                continue;
+                            }
+                            flip_x |= along_x;
+                            flip_y |= along_y;
+                            ball.push_out_of(block, self.config.ball_radius, along_x, along_y);
+                        }
+                        BlockHit::Contact(contact) => {
+                            let (normal_x, normal_y) = contact.normal;
+                            if ball.velocity_x * normal_x + ball.velocity_y * normal_y >= 0.0 {
+                                continue;
+                            }
+                            normal.0 += normal_x;
+                            normal.1 += normal_y;
+                            ball.x += normal_x * contact.depth;
+                            ball.y += normal_y * contact.depth;
+                        }
+                    }
+                    if block.is_breakable() {
+                        block.hits_remaining = block.hits_remaining.saturating_sub(1);
+                    }
+                }
+                hit_blocks.push(index);
+                if block.hits_remaining == 0 {
+                    self.destroy_block(index);
+                }
+            }
+            // Every block hit along a normal adds to one reflection, taking
+            // the restitution off the speed along it
+            let length = normal.0.hypot(normal.1);
+            if length > 0.0 {
+                let (normal_x, normal_y) = (normal.0 / length, normal.1 / length);
+                let dot = ball.velocity_x * normal_x + ball.velocity_y * normal_y;
+                if dot < 0.0 {
+                    let push = (1.0 + self.config.restitution) * dot;
+                    ball.velocity_x -= push * normal_x;
+                    ball.velocity_y -= push * normal_y;
+                    ball.keep_speed_above(self.config.min_speed);
+                }
+            }
+            if flip_x {
+                ball.velocity_x = -ball.velocity_x * self.config.restitution;
+            }
+            if flip_y {
+                ball.velocity_y = -ball.velocity_y * self.config.restitution;
+            }
+            if flip_x || flip_y {
+                ball.keep_speed_above(self.config.min_speed);
+            }
+            for index in hit_blocks {
+                self.log_collision(CollisionObject::Block, Some(index), before, ball);
+            }
+        }
+        true
+    }
+
+    /// Takes a block that lost its last hit out of play, scoring it and
+    /// throwing out its popup, particles and maybe a power-up.
+    fn destroy_block(&mut self, index: usize) {
+        let block = &mut self.blocks[index];
+        block.is_visible = false;
+        self.combo += 1;
+        let points = block.points * self.combo;
+        self.score += points;
+        self.events.push((
+            self.tick,
+            GameEvent::BlockDestroyed {
+                row: block.row,
+                col: block.col,
+            },
+        ));
+
+        let center_x = (block.rect_x1 + block.rect_x2) / 2.0;
+        let center_y = (block.rect_y1 + block.rect_y2) / 2.0;
+        self.popups
+            .push(ScorePopup::new(points, center_x, center_y));
+        self.particles.extend(Particle::burst(
+            center_x,
+            center_y,
+            self.config.palette.block(block),
+            &mut self.effects_rng,
+        ));
+
+        if self.rng.next_f32() < self.config.drop_chance {
+            self.power_ups
+                .push(PowerUp::spawn(center_x, center_y, &mut self.rng));
+        }
+    }
+
+    /// Shoots a laser up from the paddle's center, if the Laser power-up is
+    /// active and the last shot has cooled down.
+    pub fn fire(&mut self) {
+        if self.laser_ticks == 0 || self.laser_cooldown > 0 || self.state != GameState::Playing {
+            return;
+        }
+        let center = self.paddle.x + self.paddle.width / 2.0;
+        self.lasers
+            .push(Laser::new(center, self.paddle.y - LASER_HEIGHT));
+        self.laser_cooldown = LASER_COOLDOWN;
+    }
+
+    /// Whether a breakable block stands anywhere straight above the
+    /// paddle's center, for controllers that fire by themselves.
+    fn block_above_paddle(&self) -> bool {
+        let center = self.paddle.x + self.paddle.width / 2.0;
+        self.blocks.iter().any(|block| {
+            block.is_visible
+                && block.is_breakable()
+                && block.rect_x1 <= center
+                && center <= block.rect_x2
+                && block.rect_y2 <= self.paddle.y
+        })
+    }
+
+    /// Moves the lasers up, each one taking a hit off the first block it
+    /// touches and disappearing there or at the top of the field.
+    fn update_lasers(&mut self, dt: f32) {
+        let mut lasers = std::mem::take(&mut self.lasers);
+        lasers.retain_mut(|laser| {
+            laser.update_position(dt);
+            let rect = laser.rect();
+            let hit = self
+                .grid
+                .candidates(rect.0, rect.1, rect.2, rect.3)
+                .into_iter()
+                .find(|&index| {
+                    let block = &self.blocks[index];
+                    block.is_visible
+                        && rect_intersects_rect(
+                            rect,
+                            (block.rect_x1, block.rect_y1, block.rect_x2, block.rect_y2),
+                        )
+                });
+            match hit {
+                Some(index) => {
+                    let block = &mut self.blocks[index];
+                    if block.is_breakable() {
+                        block.hits_remaining = block.hits_remaining.saturating_sub(1);
+                        if block.hits_remaining == 0 {
+                            self.destroy_block(index);
+                        }
+                    }
+                    false
+                }
+                None => rect.3 >= 0.0,
+            }
+        });
+        self.lasers = lasers;
+    }
+
+    /// Advances the simulation by one physics tick of `TIME_STEP` seconds,
+    /// moving the paddle as the current controller decides.
+    pub fn step(&mut self) -> Result<(), StepError> {
+        if !self.state.is_running() || self.paused {
+            return Ok(());
+        }
+        let action = self.decide_paddle_action(self.tick_length());
+        self.step_with(action)
+    }
+
+    /// Runs one tick like `step` even while paused, for going through a
+    /// game frame by frame, and says what the controller decided. Does
+    /// nothing and returns `None` unless the game is running.
+    pub fn single_step(&mut self) -> Result<Option<PaddleAction>, StepError> {
+        if !self.state.is_running() {
+            return Ok(None);
+        }
+        let paused = std::mem::replace(&mut self.paused, false);
+        let action = self.decide_paddle_action(self.tick_length());
+        let result = self.step_with(action);
+        self.paused = paused;
+        result.map(|()| Some(action))
+    }
+
+    /// Advances one tick like `step`, with the paddle doing `action`
+    /// whatever the controller is. Fails if the tick left a ball broken.
+    pub fn step_with(&mut self, action: PaddleAction) -> Result<(), StepError> {
+        if !self.state.is_running() || self.paused {
+            return Ok(());
+        }
+        self.tick += 1;
+        self.tick(action);
+        if self.config.quantize {
+            self.quantize_state();
+        }
+        self.update_time_scale();
+        self.validate()
+    }
+
+    /// Rounds everything that moves, bar the purely visual particles.
+    fn quantize_state(&mut self) {
+        for ball in &mut self.balls {
+            ball.x = quantize(ball.x);
+            ball.y = quantize(ball.y);
+            ball.velocity_x = quantize(ball.velocity_x);
+            ball.velocity_y = quantize(ball.velocity_y);
+        }
+        self.paddle.x = quantize(self.paddle.x);
+        if let Some(top) = &mut self.top_paddle {
+            top.x = quantize(top.x);
+        }
+        for block in &mut self.blocks {
+            if block.velocity_x != 0.0 {
+                let width = quantize(block.rect_x2 - block.rect_x1);
+                block.rect_x1 = quantize(block.rect_x1);
+                block.rect_x2 = block.rect_x1 + width;
+            }
+        }
+        for power_up in &mut self.power_ups {
+            power_up.y = quantize(power_up.y);
+        }
+        for laser in &mut self.lasers {
+            laser.y = quantize(laser.y);
+        }
+    }
+
+    /// Simulated seconds covered by one tick of `TIME_STEP` real seconds.
+    pub fn tick_length(&self) -> f32 {
+        TIME_STEP * self.time_scale
+    }
+
+    /// Slows the game down for a while once only one block is left or it
+    /// reaches the last life.
+    fn update_time_scale(&mut self) {
+        let running = self.state.is_running();
+        let tension = (running && self.nearly_cleared(), running && self.lives == 1);
+        self.slow_motion_ticks = self.slow_motion_ticks.saturating_sub(1);
+        if (tension.0 && !self.tension.0) || (tension.1 && !self.tension.1) {
+            self.slow_motion_ticks = SLOW_MOTION_DURATION;
+        }
+        self.tension = tension;
+        self.time_scale = if self.slow_motion_ticks > 0 {
+            SLOW_MOTION_SCALE
+        } else {
+            1.0
+        };
+    }
+
+    /// Checks every ball is finite and either inside the field or on its
+    /// way back in.
+    pub fn validate(&self) -> Result<(), StepError> {
+        for ball in &self.balls {
+            let finite = ball.x.is_finite()
+                && ball.y.is_finite()
+                && ball.velocity_x.is_finite()
+                && ball.velocity_y.is_finite();
+            if !finite {
+                return Err(StepError::NonFiniteBall {
+                    x: ball.x,
+                    y: ball.y,
+                    velocity_x: ball.velocity_x,
+                    velocity_y: ball.velocity_y,
+                });
+            }
+
+            let escaped = (ball.x + self.config.ball_radius < 0.0 && ball.velocity_x < 0.0)
+                || (ball.x - self.config.ball_radius > self.config.width && ball.velocity_x > 0.0)
+                || (ball.y + self.config.ball_radius < 0.0 && ball.velocity_y < 0.0);
+            if escaped {
+                return Err(StepError::BallOutOfBounds {
+                    x: ball.x,
+                    y: ball.y,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// A waiting ball is served straight away unless the controller leaves
+    /// that to `serve`.
+    fn tick(&mut self, action: PaddleAction) {
+        // The clock runs in real time, slow motion or not
+        self.elapsed += TIME_STEP;
+        if self.time_left() == Some(0.0) {
+            self.state = GameState::GameOver;
+            return;
+        }
+        let dt = self.tick_length();
+
+        self.adapt_paddle_speed();
+        self.paddle
+            .move_by(action.direction(), self.config.width, dt);
+        self.move_top_paddle(dt);
+        for kind in self.catch_power_ups(dt) {
+            self.apply_power_up(kind);
+        }
+        self.update_particles(dt);
+        self.move_blocks(dt);
+
+        if self.state == GameState::Serving {
+            self.hold_ball_on_paddle();
+            // Counted in real time, like the time limit
+            self.respawn_timer = (self.respawn_timer - TIME_STEP).max(0.0);
+            if self.respawn_timer > 0.0 || !self.controller.serves_automatically() {
+                return;
+            }
+            self.serve();
+        }
+
+        self.ramp_up_speed(dt);
+        self.ghost_ticks = self.ghost_ticks.saturating_sub(1);
+        self.update_speed_effect();
+        self.laser_ticks = self.laser_ticks.saturating_sub(1);
+        self.laser_cooldown = self.laser_cooldown.saturating_sub(1);
+        if self.controller.serves_automatically() && self.block_above_paddle() {
+            self.fire();
+        }
+        self.update_lasers(dt);
+        if self.has_stuck_balls() {
+            self.stuck_ticks += 1;
+            if self.controller.serves_automatically() && self.stuck_ticks >= STICKY_HOLD {
+                self.release();
+            }
+        }
+
+        // Balls are taken out while they move so each can be updated
+        // alongside the rest of the state
+        let mut balls = std::mem::take(&mut self.balls);
+        balls.retain_mut(|ball| self.step_ball(ball, dt));
+        self.balls = balls;
+        if self.balls.is_empty() {
+            self.lose_life();
+            return;
+        }
+        if self.config.ball_collisions {
+            self.collide_balls();
+        }
+        self.record_trail();
+
+        if self.level_cleared() {
+            self.events.push((self.tick, GameEvent::LevelCleared));
+            if self.level < FINAL_LEVEL {
+                self.next_level();
+            } else {
+                self.state = GameState::Won;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::power_up::POWER_UP_SIZE;
+
+    /// A game in play on `blocks` with a single ball.
+    fn playing(blocks: Vec<Block>, ball: Ball) -> MainState {
+        playing_with(GameConfig::default(), blocks, ball)
+    }
+
+    /// Same as `playing`, set up from `config`.
+    fn playing_with(config: GameConfig, blocks: Vec<Block>, ball: Ball) -> MainState {
+        let mut game = MainState::with_blocks(blocks, config);
+        game.balls = vec![ball];
+        game.state = GameState::Playing;
+        game
+    }
+
+    /// Moves every ball through one tick, leaving the rest of the game alone.
+    fn step_balls(game: &mut MainState) {
+        let mut balls = std::mem::take(&mut game.balls);
+        balls.retain_mut(|ball| game.step_ball(ball, TIME_STEP));
+        game.balls = balls;
+    }
+
+    #[test]
+    fn fast_ball_does_not_tunnel_through_a_block() {
+        // 40 pixels a tick carries the ball from just below this thin block
+        // to just above it, so only sub-steps see the overlap
+        let block = Block::new(400.0, 117.0, 30.0, 6.0, 10, 1);
+        let mut game = playing(vec![block], Ball::new(415.0, 140.0, 0.0, -40.0 / TIME_STEP));
+        step_balls(&mut game);
+        assert!(!game.blocks[0].is_visible);
+        assert_eq!(game.score, 10);
+    }
+
+    #[test]
+    fn ball_straddling_two_blocks_reflects_once() {
+        // Stacked blocks, the ball running into both their left faces
+        let blocks = vec![
+            Block::new(100.0, 100.0, 30.0, 30.0, 10, 1),
+            Block::new(100.0, 130.0, 30.0, 30.0, 10, 1),
+        ];
+        let mut game = playing(blocks, Ball::new(86.0, 130.0, 200.0, 0.0));
+        step_balls(&mut game);
+        assert!(game.blocks.iter().all(|block| !block.is_visible));
+        assert_eq!(game.balls[0].velocity_x, -200.0);
+        assert_eq!(game.balls[0].velocity_y, 0.0);
+
+        // Side by side, the ball rising into both their bottoms
+        let blocks = vec![
+            Block::new(100.0, 100.0, 30.0, 30.0, 10, 1),
+            Block::new(130.0, 100.0, 30.0, 30.0, 10, 1),
+        ];
+        let mut game = playing(blocks, Ball::new(130.0, 146.0, 0.0, -200.0));
+        step_balls(&mut game);
+        assert!(game.blocks.iter().all(|block| !block.is_visible));
+        assert_eq!(game.balls[0].velocity_x, 0.0);
+        assert_eq!(game.balls[0].velocity_y, 200.0);
+    }
+
+    #[test]
+    fn nan_ball_position_does_not_panic() {
+        for exact in [false, true] {
+            let block = Block::new(400.0, 100.0, 30.0, 30.0, 10, 1);
+            let mut game = playing(vec![block], Ball::new(f32::NAN, f32::NAN, 0.0, -200.0));
+            game.exact_collisions = exact;
+            assert!(game.decimal_ball(&game.balls[0]).is_none());
+            let nan = f32::NAN;
+            assert!(check_circle_rectangle_collision_f32(
+                nan, nan, 15.0, 400.0, 100.0, 430.0, 130.0
+            )
+            .is_none());
+            step_balls(&mut game);
+            assert!(game.blocks[0].is_visible);
+        }
+    }
+
+    #[test]
+    fn paddle_hit_keeps_the_ball_speed() {
+        let mut game = playing(Vec::new(), Ball::new(420.0, 534.0, 100.0, 200.0));
+        let speed = game.balls[0].speed();
+        step_balls(&mut game);
+        let ball = &game.balls[0];
+        assert!(ball.velocity_y < 0.0);
+        assert!((ball.speed() - speed).abs() < 1e-3);
+    }
+
+    #[test]
+    fn rising_ball_passes_up_through_the_paddle() {
+        let mut game = playing(Vec::new(), Ball::new(400.0, 545.0, 0.0, -200.0));
+        step_balls(&mut game);
+        assert_eq!(game.balls[0].velocity_y, -200.0);
+    }
+
+    #[test]
+    fn ball_overlapping_a_wall_moves_away_from_it() {
+        let mut game = playing(Vec::new(), Ball::new(5.0, 300.0, 200.0, 50.0));
+        for _ in 0..3 {
+            let x = game.balls[0].x;
+            step_balls(&mut game);
+            assert_eq!(game.balls[0].velocity_x, 200.0);
+            assert!(game.balls[0].x > x);
+        }
+    }
+
+    #[test]
+    fn default_paddle_is_centered() {
+        for paddle_width in [80.0, 200.0] {
+            let config = GameConfig {
+                paddle_width,
+                ..GameConfig::default()
+            };
+            let game = MainState::new(config);
+            let paddle = &game.paddle;
+            assert_eq!(paddle.width, paddle_width);
+            assert_eq!(paddle.x + paddle.width / 2.0, game.config.width / 2.0);
+        }
+    }
+
+    #[test]
+    fn deeply_embedded_ball_is_pushed_clear() {
+        // Sunk 17 pixels into the bottom of a block that survives the hit
+        let block = Block::new(100.0, 100.0, 60.0, 30.0, 10, 3);
+        let mut game = playing(vec![block], Ball::new(130.0, 128.0, 0.0, -200.0));
+        step_balls(&mut game);
+        let ball = &game.balls[0];
+        assert!(game.blocks[0].is_visible);
+        assert!(ball.velocity_y > 0.0);
+        assert!(ball.y - game.config.ball_radius >= game.blocks[0].rect_y2);
+    }
+
+    #[test]
+    fn differently_sized_blocks_can_both_be_hit() {
+        let blocks = vec![
+            Block::new(100.0, 100.0, 60.0, 20.0, 10, 1),
+            Block::new(400.0, 200.0, 20.0, 50.0, 10, 1),
+        ];
+        let mut game = MainState::from_blocks(blocks);
+        game.state = GameState::Playing;
+        // One rising into the wide block, one heading right into the tall one
+        game.balls = vec![
+            Ball::new(130.0, 160.0, 0.0, -300.0),
+            Ball::new(350.0, 225.0, 300.0, 0.0),
+        ];
+        for _ in 0..30 {
+            step_balls(&mut game);
+        }
+        assert!(game.blocks.iter().all(|block| !block.is_visible));
+        assert_eq!(game.balls[0].velocity_y, 300.0);
+        assert_eq!(game.balls[1].velocity_x, -300.0);
+    }
+
+    #[test]
+    fn slow_motion_on_the_last_life_wears_off() {
+        let config = GameConfig {
+            lives: 1,
+            ..GameConfig::default()
+        };
+        let mut game = MainState::new(config);
+        game.bottom_wall = BottomBehavior::Bounce;
+        game.step_with(PaddleAction::Stay).unwrap();
+        assert_eq!(game.time_scale, SLOW_MOTION_SCALE);
+        for _ in 0..SLOW_MOTION_DURATION {
+            game.step_with(PaddleAction::Stay).unwrap();
+        }
+        assert!(game.state.is_running());
+        assert_eq!(game.time_scale, 1.0);
+        assert!((game.balls[0].speed() - game.launch_speed).abs() < 1e-3);
+    }
+
+    #[test]
+    fn same_paddle_offset_gives_the_same_angle() {
+        let game = MainState::from_blocks(Vec::new());
+        let x = game.paddle.x + game.paddle.width / 2.0 + 20.0;
+        let mut ball = Ball::new(x, game.paddle.y - game.config.ball_radius, 30.0, 200.0);
+        game.reflect_off_paddle(&mut ball);
+        let first = ball.velocity_x;
+        // Back down onto the same spot, now carrying the sideways speed
+        ball.velocity_y = -ball.velocity_y;
+        game.reflect_off_paddle(&mut ball);
+        assert!((ball.velocity_x - first).abs() < 1e-3);
+    }
+
+    #[test]
+    fn power_up_straddling_the_paddle_edge_is_caught() {
+        let mut game = MainState::from_blocks(Vec::new());
+        // Mostly off the left end of the paddle, level with its top
+        game.power_ups.push(PowerUp {
+            x: game.paddle.x - POWER_UP_SIZE * 0.75,
+            y: game.paddle.y - POWER_UP_SIZE / 2.0,
+            velocity_y: 0.0,
+            kind: PowerUpKind::ExtraLife,
+        });
+        assert_eq!(
+            game.catch_power_ups(TIME_STEP),
+            vec![PowerUpKind::ExtraLife]
+        );
+        assert!(game.power_ups.is_empty());
+    }
+
+    #[test]
+    fn combo_multiplies_consecutive_blocks() {
+        let blocks = (0..3)
+            .map(|i| Block::new(100.0 + i as f32 * 40.0, 100.0, 30.0, 30.0, 10, 1))
+            .collect();
+        let mut game = MainState::from_blocks(blocks);
+        let mut scores = Vec::new();
+        for index in 0..3 {
+            let before = game.score;
+            game.destroy_block(index);
+            scores.push(game.score - before);
+        }
+        assert_eq!(game.current_combo(), 3);
+        assert_eq!(scores, vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn reset_restores_every_block() {
+        let mut game = MainState::new(GameConfig::default());
+        for index in [0, 7, 12] {
+            game.destroy_block(index);
+        }
+        assert_eq!(game.blocks_remaining(), game.blocks.len() - 3);
+        game.reset();
+        assert!(game.blocks.iter().all(|block| block.is_visible));
+        assert_eq!(game.score, 0);
+        assert_eq!(game.lives, game.config.lives);
+    }
+
+    #[test]
+    fn ghost_ball_clears_blocks_without_turning() {
+        let blocks = vec![
+            Block::new(100.0, 100.0, 30.0, 30.0, 10, 1),
+            Block::new(100.0, 60.0, 30.0, 30.0, 10, 2),
+        ];
+        let mut game = playing(blocks, Ball::new(115.0, 150.0, 50.0, -300.0));
+        game.ghost_ticks = GHOST_DURATION;
+        for _ in 0..20 {
+            step_balls(&mut game);
+            assert_eq!(game.balls[0].velocity_x, 50.0);
+            assert_eq!(game.balls[0].velocity_y, -300.0);
+        }
+        assert!(game.blocks.iter().all(|block| !block.is_visible));
+    }
+
+    #[test]
+    fn snapshot_carries_on_the_same_game() {
+        for controller in PaddleController::ALL {
+            let mut game = MainState::new_seeded(GameConfig::default(), 7);
+            game.controller = controller.build();
+            game.serve();
+            for _ in 0..300 {
+                game.step().unwrap();
+            }
+            let mut copy = MainState::from_json(&game.to_json().unwrap()).unwrap();
+            for _ in 0..300 {
+                game.step().unwrap();
+                copy.step().unwrap();
+            }
+            assert_eq!(game.to_json().unwrap(), copy.to_json().unwrap());
+        }
+    }
+
+    #[test]
+    fn snapshot_refuses_an_unsaveable_controller() {
+        struct Still;
+        impl Controller for Still {
+            fn decide(&mut self, _: &GameView) -> PaddleAction {
+                PaddleAction::Stay
+            }
+        }
+        let mut game = MainState::new(GameConfig::default());
+        game.controller = Box::new(Still);
+        assert!(game.to_json().is_err());
+    }
+
+    #[test]
+    fn grid_size_comes_from_the_config() {
+        let config = GameConfig {
+            rows: 3,
+            cols: 6,
+            ..GameConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        assert_eq!(MainState::new(config).blocks.len(), 18);
+
+        let too_wide = GameConfig {
+            cols: 30,
+            ..GameConfig::default()
+        };
+        assert!(too_wide.validate().is_err());
+    }
+
+    #[test]
+    fn moving_block_slides_and_can_still_be_hit() {
+        let block = Block {
+            velocity_x: 120.0,
+            ..Block::new(100.0, 100.0, 30.0, 30.0, 10, 1)
+        };
+        let mut game = MainState::from_blocks(vec![block]);
+        for _ in 0..30 {
+            game.move_blocks(TIME_STEP);
+        }
+        let block = &game.blocks[0];
+        assert!((block.rect_x1 - 160.0).abs() < 1e-3);
+        assert_eq!(block.rect_x2 - block.rect_x1, 30.0);
+
+        // Rising into where the block is now, not where it started
+        game.state = GameState::Playing;
+        game.balls = vec![Ball::new(175.0, 150.0, 0.0, -300.0)];
+        for _ in 0..5 {
+            step_balls(&mut game);
+        }
+        assert!(!game.blocks[0].is_visible);
+    }
+
+    #[test]
+    fn horizontal_ball_is_nudged_off_its_line() {
+        let mut game = playing(Vec::new(), Ball::new(400.0, 300.0, 300.0, 0.0));
+        let stall_ticks = game.config.stall_ticks;
+        for _ in 0..stall_ticks {
+            step_balls(&mut game);
+        }
+        assert!(game.balls[0].velocity_y.abs() >= game.config.stall_speed);
+    }
+
+    #[test]
+    fn stuck_ball_follows_the_paddle() {
+        let mut game = playing(Vec::new(), Ball::new(0.0, 0.0, 0.0, 0.0));
+        game.balls[0].stuck_offset = Some(20.0);
+        for x in [100.0, 350.0, 600.0] {
+            game.paddle.x = x;
+            step_balls(&mut game);
+            let ball = &game.balls[0];
+            assert_eq!(ball.x, x + 20.0);
+            assert_eq!(ball.y, game.paddle.y - game.config.ball_radius);
+            assert_eq!(ball.speed(), 0.0);
+        }
+    }
+
+    #[test]
+    fn gravity_speeds_up_a_falling_ball() {
+        let config = GameConfig {
+            gravity: 300.0,
+            ..GameConfig::default()
+        };
+        let mut game = playing_with(config, Vec::new(), Ball::new(400.0, 100.0, 50.0, 20.0));
+        for _ in 0..30 {
+            let falling = game.balls[0].velocity_y;
+            step_balls(&mut game);
+            assert!(game.balls[0].velocity_y > falling);
+            assert_eq!(game.balls[0].velocity_x, 50.0);
+        }
+    }
+
+    /// Every ball's position and velocity, the paddle and the score after
+    /// each of `ticks` ticks of a seeded game.
+    fn history(config: &GameConfig, seed: u64, ticks: usize) -> Vec<(Vec<[f32; 4]>, f32, u32)> {
+        let mut game = MainState::new_seeded(config.clone(), seed);
+        (0..ticks)
+            .map(|_| {
+                game.step().unwrap();
+                let balls = game
+                    .balls
+                    .iter()
+                    .map(|ball| [ball.x, ball.y, ball.velocity_x, ball.velocity_y])
+                    .collect();
+                (balls, game.paddle.x, game.score)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn quantized_runs_of_one_seed_match() {
+        let config = GameConfig {
+            quantize: true,
+            ..GameConfig::default()
+        };
+        let first = history(&config, 3, 1200);
+        assert_eq!(first, history(&config, 3, 1200));
+        for (balls, paddle_x, _) in &first {
+            assert_eq!(*paddle_x, quantize(*paddle_x));
+            assert!(balls
+                .iter()
+                .flatten()
+                .all(|&value| value == quantize(value)));
+        }
+    }
+
+    #[test]
+    fn ball_bounces_off_the_top_paddle() {
+        let mut game = playing(Vec::new(), Ball::new(400.0, 68.0, 0.0, -200.0));
+        game.enable_top_paddle();
+        step_balls(&mut game);
+        let ball = &game.balls[0];
+        assert!(ball.velocity_y > 0.0);
+        assert!((ball.speed() - 200.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn block_faces_flip_only_their_own_axis() {
+        // Ball start and velocity for the top, bottom, left and right
+        // faces, and the velocity it should leave with
+        let approaches = [
+            ((330.0, 284.0), (100.0, 200.0), (100.0, -200.0)),
+            ((330.0, 346.0), (100.0, -200.0), (100.0, 200.0)),
+            ((284.0, 315.0), (200.0, 100.0), (-200.0, 100.0)),
+            ((376.0, 315.0), (-200.0, 100.0), (200.0, 100.0)),
+        ];
+        for ((x, y), (velocity_x, velocity_y), after) in approaches {
+            let block = Block::new(300.0, 300.0, 60.0, 30.0, 10, 5);
+            let mut game = playing(vec![block], Ball::new(x, y, velocity_x, velocity_y));
+            step_balls(&mut game);
+            let ball = &game.balls[0];
+            assert_eq!(
+                (ball.velocity_x, ball.velocity_y),
+                after,
+                "from ({}, {})",
+                x,
+                y
+            );
+            assert_eq!(game.blocks[0].hits_remaining, 4);
+        }
+    }
+
+    /// Whether `controller` gets the paddle to `ball` before it falls past.
+    fn intercepts(config: GameConfig, controller: PaddleController, ball: Ball) -> bool {
+        // A block out of the way, so the level isn't cleared straight off
+        let block = Block::new(0.0, 0.0, 10.0, 10.0, 10, 1);
+        let mut game = MainState::with_blocks(vec![block], config);
+        game.controller = controller.build();
+        game.state = GameState::Playing;
+        game.balls = vec![ball];
+        for _ in 0..600 {
+            game.step().unwrap();
+            for (_, event) in game.drain_events() {
+                match event {
+                    GameEvent::PaddleHit => return true,
+                    GameEvent::BallLost => return false,
+                    _ => {}
+                }
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn ai_paddle_keeps_up_with_a_fast_ball() {
+        let ball = Ball::new(400.0, 300.0, 600.0, 150.0);
+        let fixed = GameConfig {
+            ai_speed_factor: 0.0,
+            ..GameConfig::default()
+        };
+        let follow = PaddleController::FollowBall;
+        assert!(!intercepts(fixed, follow, ball.clone()));
+        assert!(intercepts(GameConfig::default(), follow, ball.clone()));
+        assert!(intercepts(
+            GameConfig::default(),
+            PaddleController::Predictive,
+            ball
+        ));
+    }
+
+    #[test]
+    fn far_edge_hit_deflects_steeply() {
+        let mut game = MainState::from_blocks(Vec::new());
+        game.state = GameState::Playing;
+        let (center, right_end) = (
+            game.paddle.x + game.paddle.width / 2.0,
+            game.paddle.x + game.paddle.width - 1.0,
+        );
+        game.balls = vec![
+            Ball::new(center, 532.0, 0.0, 300.0),
+            Ball::new(right_end, 532.0, 0.0, 300.0),
+        ];
+        step_balls(&mut game);
+        let angle = |ball: &Ball| ball.velocity_x.atan2(-ball.velocity_y).to_degrees();
+        assert!(angle(&game.balls[0]).abs() < 1.0);
+        assert!(angle(&game.balls[1]) > 50.0);
+    }
+
+    /// Each standing block's cell, corners and hits, in row then column order.
+    fn block_cells(game: &MainState) -> Vec<((u32, u32), [f32; 4], u32)> {
+        let mut cells: Vec<_> = game
+            .blocks
+            .iter()
+            .filter(|block| block.is_visible)
+            .map(|block| {
+                let rect = [block.rect_x1, block.rect_y1, block.rect_x2, block.rect_y2];
+                ((block.row, block.col), rect, block.hits_remaining)
+            })
+            .collect();
+        cells.sort_by_key(|&(cell, _, _)| cell);
+        cells
+    }
+
+    #[test]
+    fn edited_layout_round_trips_through_a_file() {
+        let config = GameConfig {
+            block_width: 50.0,
+            block_height: 20.0,
+            cols: 8,
+            ..GameConfig::default()
+        };
+        let mut game = MainState::new(config.clone());
+        game.start_editing();
+        // Take out the top-left block and add one in a new row below
+        let (x, y) = (game.blocks[0].rect_x1 + 1.0, game.blocks[0].rect_y1 + 1.0);
+        game.toggle_block_at(x, y);
+        let step = config.block_height + config.fitted_spacing();
+        game.toggle_block_at(x + 60.0, y + config.rows as f32 * step);
+        assert_eq!(game.blocks.len(), 40);
+
+        let path = std::env::temp_dir().join(format!("arkanoid-layout-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        game.save_layout(path).unwrap();
+        let loaded = MainState::from_layout(path, config);
+        std::fs::remove_file(path).unwrap();
+
+        let (edited, loaded) = (block_cells(&game), block_cells(&loaded.unwrap()));
+        assert_eq!(edited, loaded);
+    }
+
+    #[test]
+    fn default_grid_is_centered() {
+        for (cols, block_spacing) in [(10, 5.0), (7, 12.0), (1, 5.0)] {
+            let config = GameConfig {
+                cols,
+                block_spacing,
+                ..GameConfig::default()
+            };
+            let game = MainState::new(config);
+            let left = game
+                .blocks
+                .iter()
+                .map(|b| b.rect_x1)
+                .fold(f32::INFINITY, f32::min);
+            let right = game.blocks.iter().map(|b| b.rect_x2).fold(0.0, f32::max);
+            assert!((left - (game.config.width - right)).abs() < 1e-3);
+            // Every block has its mirror image across the center
+            for block in &game.blocks {
+                let mirrored = game.config.width - block.rect_x2;
+                assert!(game
+                    .blocks
+                    .iter()
+                    .any(|other| (other.rect_x1 - mirrored).abs() < 1e-3
+                        && other.rect_y1 == block.rect_y1));
+            }
+            assert!(game
+                .blocks
+                .iter()
+                .all(|b| b.rect_y1 >= game.config.top_margin));
+        }
+    }
+
+    #[test]
+    fn restitution_takes_speed_off_a_bounce() {
+        let config = GameConfig {
+            restitution: 0.8,
+            ..GameConfig::default()
+        };
+        let mut game = playing_with(config, Vec::new(), Ball::new(20.0, 300.0, -300.0, 0.0));
+        step_balls(&mut game);
+        assert!((game.balls[0].speed() - 240.0).abs() < 1e-3);
+        assert_eq!(game.balls[0].velocity_x, 240.0);
+    }
+
+    #[test]
+    fn slow_ball_reduces_the_speed() {
+        let mut game = playing(Vec::new(), Ball::new(400.0, 300.0, 300.0, -400.0));
+        game.apply_power_up(PowerUpKind::SlowBall);
+        let ball = &game.balls[0];
+        assert!((ball.speed() - 500.0 * game.config.slow_ball_factor).abs() < 1e-3);
+        // Still heading the same way
+        assert!((ball.velocity_x / ball.velocity_y + 0.75).abs() < 1e-5);
+    }
+
+    #[test]
+    fn split_adds_two_balls_up_to_the_cap() {
+        let config = GameConfig {
+            max_balls: 4,
+            ..GameConfig::default()
+        };
+        let mut game = playing_with(config, Vec::new(), Ball::new(400.0, 300.0, 0.0, -300.0));
+        for expected in [3, 4, 4] {
+            game.power_ups.push(PowerUp {
+                x: game.paddle.x + 10.0,
+                y: game.paddle.y,
+                velocity_y: 0.0,
+                kind: PowerUpKind::Split,
+            });
+            for kind in game.catch_power_ups(TIME_STEP) {
+                game.apply_power_up(kind);
+            }
+            assert_eq!(game.balls.len(), expected);
+        }
+        for ball in &game.balls {
+            assert!((ball.speed() - 300.0).abs() < 1e-3);
+        }
+    }
+
+    /// Where a ball of `radius` heading right from the center turns back
+    /// off the right wall.
+    fn right_wall_bounce_x(radius: f32) -> f32 {
+        let config = GameConfig {
+            ball_radius: radius,
+            ..GameConfig::default()
+        };
+        let mut game = playing_with(config, Vec::new(), Ball::new(400.0, 300.0, 300.0, 0.0));
+        while game.balls[0].velocity_x > 0.0 {
+            step_balls(&mut game);
+        }
+        game.balls[0].x
+    }
+
+    #[test]
+    fn ball_radius_sets_where_it_meets_the_wall() {
+        let travel = 300.0 * TIME_STEP;
+        for radius in [5.0, 25.0] {
+            let edge = right_wall_bounce_x(radius) + radius;
+            assert!(edge >= 800.0 && edge < 800.0 + travel, "radius {}", radius);
+        }
+        let gap = right_wall_bounce_x(5.0) - right_wall_bounce_x(25.0);
+        assert!((gap - 20.0).abs() <= travel);
+    }
+
+    #[test]
+    fn ball_bounces_off_the_paddle_side() {
+        let config = GameConfig {
+            paddle_rect_collision: true,
+            ..GameConfig::default()
+        };
+        // Level with the paddle's left end, heading right into it
+        let (x, y) = (343.0, 555.0);
+        let mut game = playing_with(config, Vec::new(), Ball::new(x, y, 200.0, 0.0));
+        step_balls(&mut game);
+        let ball = &game.balls[0];
+        assert_eq!((ball.velocity_x, ball.velocity_y), (-200.0, 0.0));
+        assert!(ball.x + game.config.ball_radius <= game.paddle.x);
+    }
+
+    #[test]
+    fn wall_jitter_stays_deterministic_per_seed() {
+        let plain = GameConfig::default();
+        let jittered = GameConfig {
+            wall_jitter: 2.0,
+            ..GameConfig::default()
+        };
+        let without = history(&plain, 11, 1200);
+        assert_eq!(without, history(&plain, 11, 1200));
+        let with = history(&jittered, 11, 1200);
+        assert_eq!(with, history(&jittered, 11, 1200));
+        assert_ne!(with, without);
+    }
+
+    #[test]
+    fn ai_aims_the_bounce_at_the_busiest_column() {
+        let block = Block::new(600.0, 100.0, 30.0, 30.0, 10, 1);
+        let game = MainState::from_blocks(vec![block]);
+        let center = game.view(TIME_STEP).aim_point(400.0);
+        assert!(center < 400.0);
+        let paddle = &game.paddle;
+        let aimed = Paddle::new(
+            center - paddle.width / 2.0,
+            paddle.y,
+            paddle.width,
+            10.0,
+            0.0,
+        );
+        let angle = aimed.bounce_angle(400.0, 0.0).to_radians();
+        // Leaving from the paddle line, it reaches the block's bottom under its middle
+        let x = 400.0 + (paddle.y - 130.0) * angle.tan();
+        assert!((x - 615.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn predictive_paddle_clears_the_first_level() {
+        // Each of these takes 7500-9000 ticks; a paddle that keeps sending
+        // the ball up the same column never gets there
+        for seed in 0..3 {
+            let mut game = MainState::new_seeded(GameConfig::default(), seed);
+            game.controller = PaddleController::Predictive.build();
+            let mut ticks = 0;
+            while game.current_level() == 1 && ticks < 12_000 {
+                game.step().unwrap();
+                ticks += 1;
+            }
+            assert_eq!(game.current_level(), 2, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn vertical_ball_is_nudged_sideways() {
+        // Bouncing straight between the top wall and the paddle's dead center
+        let mut game = playing(Vec::new(), Ball::new(400.0, 300.0, 0.0, -300.0));
+        let stall_ticks = game.config.stall_ticks;
+        for _ in 0..stall_ticks {
+            step_balls(&mut game);
+        }
+        assert!(game.balls[0].velocity_x.abs() >= game.config.stall_speed);
+    }
+
+    #[test]
+    fn speed_effect_caught_before_launch_wears_off_to_launch_speed() {
+        // Caught while waiting to be served
+        let mut game = MainState::from_blocks(Vec::new());
+        game.apply_power_up(PowerUpKind::SlowBall);
+        game.serve();
+        let slowed = game.launch_speed * game.config.slow_ball_factor;
+        assert!((game.balls[0].speed() - slowed).abs() < 1e-3);
+        for _ in 0..SPEED_EFFECT_DURATION {
+            game.update_speed_effect();
+        }
+        assert!((game.balls[0].speed() - game.launch_speed).abs() < 1e-3);
+
+        // Caught while stuck to a sticky paddle
+        let mut game = playing(Vec::new(), Ball::new(400.0, 535.0, 0.0, 0.0));
+        game.balls[0].stuck_offset = Some(40.0);
+        game.apply_power_up(PowerUpKind::FastBall);
+        game.release();
+        let hastened = game.launch_speed * game.config.fast_ball_factor;
+        assert!((game.balls[0].speed() - hastened).abs() < 1e-3);
+        for _ in 0..SPEED_EFFECT_DURATION {
+            game.update_speed_effect();
+        }
+        assert!((game.balls[0].speed() - game.launch_speed).abs() < 1e-3);
+    }
+
+    #[test]
+    fn quantize_rounds_the_top_paddle_and_lasers() {
+        // Paddles covering a fraction of a pixel a tick and a laser starting
+        // off the rounding grid, so neither is rounded by chance
+        let config = GameConfig {
+            quantize: true,
+            paddle_speed: 310.0,
+            ..GameConfig::default()
+        };
+        // A block out of the way keeps the level going
+        let block = Block::new(0.0, 0.0, 10.0, 10.0, 10, 1);
+        let ball = Ball::new(300.0, 400.0, -50.0, -300.0);
+        let mut game = playing_with(config, vec![block], ball);
+        game.enable_top_paddle();
+        game.lasers.push(Laser::new(400.0, 500.0004));
+        for _ in 0..20 {
+            game.step_with(PaddleAction::Stay).unwrap();
+            let top = game.top_paddle.as_ref().unwrap();
+            assert_eq!(top.x, quantize(top.x));
+            let laser = &game.lasers[0];
+            assert_eq!(laser.y, quantize(laser.y));
+        }
+    }
+}